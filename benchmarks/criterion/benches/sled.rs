@@ -78,6 +78,35 @@ fn sled_bulk_load(c: &mut Criterion) {
     }
 }
 
+fn sled_sorted_load(c: &mut Criterion) {
+    const SIZE: u32 = 100_000;
+
+    c.bench_function("sorted load via insert", |b| {
+        b.iter(|| {
+            let db = Config::new()
+                .temporary(true)
+                .flush_every_ms(None)
+                .open()
+                .unwrap();
+            for i in 0..SIZE {
+                db.insert(i.to_be_bytes(), vec![]).unwrap();
+            }
+        })
+    });
+
+    c.bench_function("sorted load via load_sorted", |b| {
+        b.iter(|| {
+            let db = Config::new()
+                .temporary(true)
+                .flush_every_ms(None)
+                .open()
+                .unwrap();
+            db.load_sorted((0..SIZE).map(|i| (i.to_be_bytes(), vec![])))
+                .unwrap();
+        })
+    });
+}
+
 fn sled_monotonic_crud(c: &mut Criterion) {
     let db = Config::new().temporary(true).flush_every_ms(None).open().unwrap();
 
@@ -133,6 +162,25 @@ fn sled_random_crud(c: &mut Criterion) {
     });
 }
 
+fn sled_large_value_gets(c: &mut Criterion) {
+    let db = Config::new().temporary(true).flush_every_ms(None).open().unwrap();
+
+    db.insert(b"large", vec![1; 1024 * 1024]).unwrap();
+
+    c.bench_function("get 1mb value", |b| {
+        b.iter(|| {
+            let value = db.get(b"large").unwrap().unwrap();
+            value[0]
+        })
+    });
+
+    c.bench_function("get_zero_copy 1mb value", |b| {
+        b.iter(|| {
+            db.get_zero_copy(b"large", |value| value.unwrap()[0]).unwrap()
+        })
+    });
+}
+
 fn sled_empty_opens(c: &mut Criterion) {
     let _ = std::fs::remove_dir_all("empty_opens");
     c.bench_function("empty opens", |b| {
@@ -150,8 +198,10 @@ fn sled_empty_opens(c: &mut Criterion) {
 criterion_group!(
     benches,
     sled_bulk_load,
+    sled_sorted_load,
     sled_monotonic_crud,
     sled_random_crud,
+    sled_large_value_gets,
     sled_empty_opens
 );
 criterion_main!(benches);
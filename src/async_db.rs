@@ -0,0 +1,135 @@
+use std::{
+    future::Future,
+    ops::{Bound, RangeBounds},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use super::*;
+
+/// The future returned by each [`AsyncDb`] operation, which
+/// resolves to the result of the operation once the
+/// threadpool has run it.
+#[derive(Debug)]
+pub struct DbFuture<T> {
+    promise: OneShot<Result<T>>,
+}
+
+impl<T> Future for DbFuture<T> {
+    type Output = Result<T>;
+
+    fn poll(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Self::Output> {
+        Pin::new(&mut self.promise).poll(cx).map(|result| {
+            result.unwrap_or(Err(Error::ReportableBug(
+                "threadpool failed to complete action before shutdown",
+            )))
+        })
+    }
+}
+
+/// A thin asynchronous wrapper around a [`Db`], available with
+/// the `async` feature, that runs each operation on sled's
+/// blocking threadpool so that an async runtime's worker
+/// threads are never blocked on reading pages from disk or
+/// on waiting for a flush. It does not depend on any
+/// particular runtime.
+///
+/// Each operation is handed to the threadpool as soon as the
+/// method is called, not when the returned future is first
+/// polled. Dropping the future only discards the result: the
+/// operation still runs to completion, so a write is either
+/// fully applied or, if the call itself failed, not at all.
+///
+/// The wrapped `Db` remains the source of truth, and may be
+/// used directly through [`AsyncDb::db`] alongside this
+/// wrapper, for example for batches and transactions.
+#[derive(Debug, Clone)]
+pub struct AsyncDb {
+    db: Db,
+}
+
+impl From<Db> for AsyncDb {
+    fn from(db: Db) -> AsyncDb {
+        AsyncDb { db }
+    }
+}
+
+impl AsyncDb {
+    /// Wrap a `Db` for asynchronous use.
+    pub fn new(db: Db) -> AsyncDb {
+        AsyncDb { db }
+    }
+
+    /// Returns the wrapped `Db`.
+    pub fn db(&self) -> &Db {
+        &self.db
+    }
+
+    fn spawn<F, T>(&self, work: F) -> DbFuture<T>
+    where
+        F: FnOnce(&Db) -> Result<T> + Send + 'static,
+        T: Send + 'static,
+    {
+        let db = self.db.clone();
+        DbFuture { promise: threadpool::spawn(move || work(&db)) }
+    }
+
+    /// Asynchronous version of [`Tree::get`].
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> DbFuture<Option<IVec>> {
+        let key = IVec::from(key.as_ref());
+        self.spawn(move |db| db.get(key))
+    }
+
+    /// Asynchronous version of [`Tree::contains_key`].
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> DbFuture<bool> {
+        let key = IVec::from(key.as_ref());
+        self.spawn(move |db| db.contains_key(key))
+    }
+
+    /// Asynchronous version of [`Tree::insert`].
+    pub fn insert<K, V>(&self, key: K, value: V) -> DbFuture<Option<IVec>>
+    where
+        K: AsRef<[u8]>,
+        V: Into<IVec>,
+    {
+        let key = IVec::from(key.as_ref());
+        let value = value.into();
+        self.spawn(move |db| db.insert(key, value))
+    }
+
+    /// Asynchronous version of [`Tree::remove`].
+    pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> DbFuture<Option<IVec>> {
+        let key = IVec::from(key.as_ref());
+        self.spawn(move |db| db.remove(key))
+    }
+
+    /// Asynchronous version of [`Tree::apply_batch`].
+    pub fn apply_batch(&self, batch: Batch) -> DbFuture<()> {
+        self.spawn(move |db| db.apply_batch(batch))
+    }
+
+    /// Collects every key and value within `range`, like
+    /// collecting [`Tree::range`]. The whole range is held in
+    /// memory, so keep ranges bounded.
+    pub fn range<K, R>(&self, range: R) -> DbFuture<Vec<(IVec, IVec)>>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let to_ivec = |bound: Bound<&K>| match bound {
+            Bound::Included(k) => Bound::Included(IVec::from(k.as_ref())),
+            Bound::Excluded(k) => Bound::Excluded(IVec::from(k.as_ref())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let bounds = (to_ivec(range.start_bound()), to_ivec(range.end_bound()));
+        self.spawn(move |db| db.range(bounds).collect())
+    }
+
+    /// Asynchronous version of [`Tree::flush`].
+    pub fn flush(&self) -> DbFuture<usize> {
+        self.spawn(|db| db.flush())
+    }
+}
@@ -1,5 +1,7 @@
 #![allow(unused_results)]
 
+use std::iter::FromIterator;
+
 use super::*;
 
 /// A batch of updates that will
@@ -58,3 +60,82 @@ impl Batch {
         Some(inner.as_ref())
     }
 }
+
+impl<K, V> Extend<(K, V)> for Batch
+where
+    K: Into<IVec>,
+    V: Into<IVec>,
+{
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, pairs: I) {
+        for (k, v) in pairs {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for Batch
+where
+    K: Into<IVec>,
+    V: Into<IVec>,
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(pairs: I) -> Batch {
+        let mut batch = Batch::default();
+        batch.extend(pairs);
+        batch
+    }
+}
+
+/// A batch of updates that will be applied atomically
+/// to the `Tree`, but only if every key it was built
+/// with still holds the value that the write expects.
+/// This is like a `compare_and_swap` over several keys.
+///
+/// # Examples
+///
+/// ```
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// # let config = sled::Config::new().temporary(true);
+/// # let db = config.open()?;
+/// db.insert("a", "1")?;
+///
+/// let mut batch = sled::ConditionalBatch::default();
+/// batch.insert_if("a", Some("1"), "2");
+/// batch.insert_if("b", None as Option<&[u8]>, "1");
+///
+/// db.apply_conditional_batch(batch)?.unwrap();
+/// assert_eq!(db.get("a")?, Some(sled::IVec::from("2")));
+/// assert_eq!(db.get("b")?, Some(sled::IVec::from("1")));
+/// # Ok(()) }
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ConditionalBatch {
+    pub(crate) expected: Map<IVec, Option<IVec>>,
+    pub(crate) batch: Batch,
+}
+
+impl ConditionalBatch {
+    /// Set a key to a new value if its current value
+    /// is `expected`, where `None` means it is absent.
+    pub fn insert_if<K, E, V>(&mut self, key: K, expected: Option<E>, value: V)
+    where
+        K: Into<IVec>,
+        E: Into<IVec>,
+        V: Into<IVec>,
+    {
+        let key = key.into();
+        self.expected.insert(key.clone(), expected.map(Into::into));
+        self.batch.insert(key, value);
+    }
+
+    /// Remove a key if its current value is `expected`,
+    /// where `None` means it is absent.
+    pub fn remove_if<K, E>(&mut self, key: K, expected: Option<E>)
+    where
+        K: Into<IVec>,
+        E: Into<IVec>,
+    {
+        let key = key.into();
+        self.expected.insert(key.clone(), expected.map(Into::into));
+        self.batch.remove(key);
+    }
+}
@@ -30,6 +30,92 @@ pub enum Mode {
     HighThroughput,
 }
 
+/// Where a full node is split in two, which determines how
+/// full the resulting nodes are left. The split key is still
+/// shortened as much as possible for leaves, so index nodes
+/// stay small regardless of the policy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitPolicy {
+    /// Split at a point weighted by where recent writes to the
+    /// node landed. This adapts to both ascending and
+    /// descending workloads, and is the default.
+    Adaptive,
+    /// Split in the middle, leaving both nodes half full.
+    Midpoint,
+    /// Move only the last item to the new right node, leaving
+    /// the left node full. The new right node is then allowed
+    /// to grow to twice the size of the left one before it is
+    /// split again. This suits keys that are inserted in
+    /// ascending order, where the left node will never be
+    /// written to again.
+    AppendOptimized,
+    /// Keep only the first item in the left node, leaving the
+    /// new right node full, and let the left node grow to twice
+    /// the size of the right one before it is split again. This
+    /// suits keys that are inserted in descending order.
+    PrependOptimized,
+    /// Keep this fraction of the items, between 0.0 and 1.0
+    /// exclusive, in the left node.
+    Ratio(f32),
+}
+
+/// Which leaves are read into the cache while a `Db` is being
+/// opened, so that the first requests after a restart do not
+/// each have to page in the leaves they touch. Warming never
+/// reads more than `cache_capacity` bytes of leaves, so that it
+/// does not evict what it has just read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WarmPolicy {
+    /// Leave every leaf on disk until it is first used. This is
+    /// the default.
+    None,
+    /// Read the leaves of every tree, in key order, until the
+    /// cache is full.
+    Full,
+    /// Read the leaves holding keys that start with this
+    /// prefix, in every tree.
+    Prefix(Vec<u8>),
+    /// Like `Full`, but stop after reading this many bytes of
+    /// leaves.
+    Budget(usize),
+}
+
+/// A callback invoked with the page id and in-memory size
+/// of every page evicted from the cache.
+#[derive(Clone)]
+pub(crate) struct OnEvict(Arc<dyn Fn(u64, usize) + Send + Sync>);
+
+impl OnEvict {
+    pub(crate) fn call(&self, pid: PageId, size: usize) {
+        (self.0)(pid, size)
+    }
+}
+
+impl Debug for OnEvict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnEvict")
+    }
+}
+
+/// A callback invoked with the progress of recovery while
+/// a database is opened.
+#[derive(Clone)]
+pub(crate) struct OnRecoveryProgress(
+    Arc<dyn Fn(RecoveryProgress) + Send + Sync>,
+);
+
+impl OnRecoveryProgress {
+    pub(crate) fn call(&self, progress: RecoveryProgress) {
+        (self.0)(progress)
+    }
+}
+
+impl Debug for OnRecoveryProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("OnRecoveryProgress")
+    }
+}
+
 /// A persisted configuration about high-level
 /// storage file information
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
@@ -190,6 +276,10 @@ pub struct Inner {
     #[doc(hidden)]
     pub flush_every_ms: Option<u64>,
     #[doc(hidden)]
+    pub flusher_thread_name: String,
+    #[doc(hidden)]
+    pub flusher_stack_size: Option<usize>,
+    #[doc(hidden)]
     pub segment_size: usize,
     #[doc(hidden)]
     pub path: PathBuf,
@@ -198,6 +288,14 @@ pub struct Inner {
     #[doc(hidden)]
     pub mode: Mode,
     #[doc(hidden)]
+    pub split_policy: SplitPolicy,
+    #[doc(hidden)]
+    pub warm_on_open: WarmPolicy,
+    #[doc(hidden)]
+    pub max_key_size: usize,
+    #[doc(hidden)]
+    pub max_value_size: usize,
+    #[doc(hidden)]
     pub temporary: bool,
     #[doc(hidden)]
     pub use_compression: bool,
@@ -209,6 +307,8 @@ pub struct Inner {
     pub snapshot_after_ops: u64,
     #[doc(hidden)]
     pub version: (usize, usize),
+    pub(crate) on_evict: Option<OnEvict>,
+    pub(crate) recovery_progress: Option<OnRecoveryProgress>,
     tmp_path: PathBuf,
     pub(crate) global_error: Arc<Atomic<Error>>,
     #[cfg(feature = "event_log")]
@@ -225,14 +325,22 @@ impl Default for Inner {
             create_new: false,
             cache_capacity: 1024 * 1024 * 1024, // 1gb
             mode: Mode::LowSpace,
+            split_policy: SplitPolicy::Adaptive,
+            warm_on_open: WarmPolicy::None,
+            max_key_size: 64 * 1024, // 64kb
+            max_value_size: 1024 * 1024 * 1024, // 1gb
             use_compression: false,
             compression_factor: 5,
             temporary: false,
             version: crate_version(),
+            on_evict: None,
+            recovery_progress: None,
 
             // useful in testing
             segment_size: 512 * 1024, // 512kb in bytes
             flush_every_ms: Some(500),
+            flusher_thread_name: "log flusher".to_owned(),
+            flusher_stack_size: None,
             idgen_persist_interval: 1_000_000,
             snapshot_after_ops: if cfg!(feature = "for-internal-testing-only") {
                 10
@@ -319,6 +427,80 @@ impl Config {
         self
     }
 
+    /// Like `temporary(true)`, but creates the uniquely named
+    /// temporary database directory under `base` instead of
+    /// `/dev/shm` on linux or the system temp directory
+    /// elsewhere. The directory is removed once the last
+    /// `Db`, `Tree`, and background thread using it are gone.
+    /// `base` itself is created if needed and never removed.
+    /// If a `path` is also set, that path is used instead.
+    #[doc(alias = "tmp_in")]
+    pub fn temporary_in<P: AsRef<Path>>(mut self, base: P) -> Config {
+        if Arc::strong_count(&self.0) != 1 {
+            error!(
+                "config has already been used to start \
+                 the system and probably should not be \
+                 mutated",
+            );
+        }
+        let m = Arc::make_mut(&mut self.0);
+        m.temporary = true;
+        m.tmp_path = base.as_ref().join(Config::gen_temp_name());
+        self
+    }
+
+    /// Register a callback that is invoked with the page id
+    /// and approximate in-memory size of each page that the
+    /// cache evicts to stay within `cache_capacity`. This is
+    /// useful for tuning `cache_capacity`, and may be combined
+    /// with `Db::cache_stats`.
+    ///
+    /// The callback runs on the thread that triggered the
+    /// eviction, in the middle of a tree operation, so it
+    /// should be cheap and must not call back into the
+    /// database.
+    pub fn on_evict<F>(mut self, f: F) -> Config
+    where
+        F: Fn(u64, usize) + Send + Sync + 'static,
+    {
+        if Arc::strong_count(&self.0) != 1 {
+            error!(
+                "config has already been used to start \
+                 the system and probably should not be \
+                 mutated",
+            );
+        }
+        let m = Arc::make_mut(&mut self.0);
+        m.on_evict = Some(OnEvict(Arc::new(f)));
+        self
+    }
+
+    /// Register a callback that is periodically invoked with
+    /// the number of log entries replayed, the bytes of log
+    /// scanned and the time spent so far while recovering the
+    /// database during `open`, and once more when recovery
+    /// finishes. This can be used to report progress while
+    /// opening a large database. The final values are also
+    /// available later from `Db::recovery_stats`.
+    ///
+    /// The callback runs on the thread opening the database,
+    /// and must not call back into it.
+    pub fn recovery_progress<F>(mut self, f: F) -> Config
+    where
+        F: Fn(RecoveryProgress) + Send + Sync + 'static,
+    {
+        if Arc::strong_count(&self.0) != 1 {
+            error!(
+                "config has already been used to start \
+                 the system and probably should not be \
+                 mutated",
+            );
+        }
+        let m = Arc::make_mut(&mut self.0);
+        m.recovery_progress = Some(OnRecoveryProgress(Arc::new(f)));
+        self
+    }
+
     /// A testing-only method for reducing the io-buffer size
     /// to trigger correctness-critical behavior more often
     /// by shrinking the buffer size. Don't rely on this.
@@ -388,7 +570,7 @@ impl Config {
         self
     }
 
-    fn gen_temp_path() -> PathBuf {
+    fn gen_temp_name() -> String {
         use std::time::SystemTime;
 
         static SALT_COUNTER: AtomicUsize = AtomicUsize::new(0);
@@ -409,11 +591,17 @@ impl Config {
 
         let salt = (pid << 16) + now + seed;
 
+        format!("pagecache.tmp.{}", salt)
+    }
+
+    fn gen_temp_path() -> PathBuf {
+        let name = Config::gen_temp_name();
+
         if cfg!(target_os = "linux") {
             // use shared memory for temporary linux files
-            format!("/dev/shm/pagecache.tmp.{}", salt).into()
+            Path::new("/dev/shm").join(name)
         } else {
-            std::env::temp_dir().join(format!("pagecache.tmp.{}", salt))
+            std::env::temp_dir().join(name)
         }
     }
 
@@ -442,6 +630,28 @@ impl Config {
             Mode,
             "specify whether the system should run in \"small\" or \"fast\" mode"
         ),
+        (
+            split_policy,
+            SplitPolicy,
+            "where full nodes are split, see `SplitPolicy`"
+        ),
+        (
+            warm_on_open,
+            WarmPolicy,
+            "which leaves to read into the cache when opening, see `WarmPolicy`"
+        ),
+        (
+            max_key_size,
+            usize,
+            "the largest key in bytes that writes will accept, 64kb by default. \
+             a database that already holds larger keys needs a larger limit \
+             to set those keys again, but they can still be read and removed"
+        ),
+        (
+            max_value_size,
+            usize,
+            "the largest value in bytes that writes will accept, 1gb by default"
+        ),
         (use_compression, bool, "whether to use zstd compression"),
         (
             compression_factor,
@@ -462,6 +672,16 @@ impl Config {
             snapshot_after_ops,
             u64,
             "take a fuzzy snapshot of pagecache metadata after this many ops"
+        ),
+        (
+            flusher_thread_name,
+            String,
+            "the name of the background flusher thread, \"log flusher\" by default. most platforms only show the first 15 bytes"
+        ),
+        (
+            flusher_stack_size,
+            Option<usize>,
+            "the stack size in bytes of the background flusher thread, or None for the platform default"
         )
     );
 
@@ -493,6 +713,16 @@ impl Config {
             self.compression_factor <= 22,
             "compression_factor must be <= 22"
         );
+        if let SplitPolicy::Ratio(ratio) = self.split_policy {
+            supported!(
+                ratio > 0.0 && ratio < 1.0,
+                "SplitPolicy::Ratio must be between 0.0 and 1.0 exclusive"
+            );
+        }
+        supported!(
+            self.max_key_size > 0 && self.max_value_size > 0,
+            "max_key_size and max_value_size must be above 0"
+        );
         supported!(
             self.idgen_persist_interval > 0,
             "idgen_persist_interval must be above 0"
@@ -1,9 +1,11 @@
-use std::ops::Deref;
+use std::ops::{Bound, Deref};
 
 use crate::*;
 
 const DEFAULT_TREE_ID: &[u8] = b"__sled__default";
 
+type ExportReceiver = std::sync::mpsc::Receiver<(IVec, IVec, IVec)>;
+
 /// The `sled` embedded database! Implements
 /// `Deref<Target = sled::Tree>` to refer to
 /// a default keyspace / namespace / bucket.
@@ -28,6 +30,15 @@ impl Deref for Db {
     }
 }
 
+impl IntoIterator for &'_ Db {
+    type Item = Result<(IVec, IVec)>;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        self.iter()
+    }
+}
+
 impl Debug for Db {
     fn fmt(
         &self,
@@ -56,9 +67,12 @@ impl Db {
         #[cfg(not(miri))]
         {
             let flusher_pagecache = context.pagecache.clone();
+            let flusher_name = context.flusher_thread_name.clone();
+            let flusher_stack_size = context.flusher_stack_size;
             let flusher = context.flush_every_ms.map(move |fem| {
                 flusher::Flusher::new(
-                    "log flusher".to_owned(),
+                    flusher_name,
+                    flusher_stack_size,
                     flusher_pagecache,
                     fem,
                 )
@@ -95,14 +109,51 @@ impl Db {
         #[cfg(feature = "event_log")]
         {
             for (_name, tree) in ret.tenants.read().iter() {
-                tree.verify_integrity()?;
+                assert_eq!(tree.verify_integrity()?, vec![]);
             }
             ret.context.event_log.verify();
+
+            // verification reads every node, so page them back out
+            // to leave the cache as it would be without the event
+            // log, before warming decides what to read in.
+            ret.context.pagecache.page_out_all()?;
         }
 
+        ret.warm_on_open()?;
+
         Ok(ret)
     }
 
+    // Reads leaves into the cache as requested by
+    // `Config::warm_on_open`, with all trees sharing one budget.
+    fn warm_on_open(&self) -> Result<()> {
+        let capacity = self.context.cache_capacity as u64;
+        let (lo, hi, mut budget) = match &self.context.warm_on_open {
+            WarmPolicy::None => return Ok(()),
+            WarmPolicy::Full => (Bound::Unbounded, Bound::Unbounded, capacity),
+            WarmPolicy::Prefix(prefix) => {
+                let Iter { lo, hi, .. } = self.scan_prefix(prefix);
+                (lo, hi, capacity)
+            }
+            WarmPolicy::Budget(bytes) => (
+                Bound::Unbounded,
+                Bound::Unbounded,
+                capacity.min(*bytes as u64),
+            ),
+        };
+
+        let trees: Vec<Tree> = self.tenants.read().values().cloned().collect();
+        for tree in trees {
+            if budget == 0 {
+                break;
+            }
+            let warmed = tree.warm_inner(lo.clone(), hi.clone(), budget)?;
+            budget = budget.saturating_sub(warmed);
+        }
+
+        Ok(())
+    }
+
     /// Open or create a new disk-backed Tree with its own keyspace,
     /// accessible from the `Db` via the provided identifier.
     pub fn open_tree<V: AsRef<[u8]>>(&self, name: V) -> Result<Tree> {
@@ -316,8 +367,8 @@ impl Db {
     /// assert_eq!(old.checksum()?, new.checksum()?);
     /// # drop(old);
     /// # drop(new);
-    /// # std::fs::remove_file("my_old__db");
-    /// # std::fs::remove_file("my_new__db");
+    /// # let _ = std::fs::remove_dir_all("my_old__db");
+    /// # let _ = std::fs::remove_dir_all("my_new__db");
     /// # Ok(()) }
     /// ```
     pub fn export(
@@ -382,8 +433,8 @@ impl Db {
     /// assert_eq!(old.checksum()?, new.checksum()?);
     /// # drop(old);
     /// # drop(new);
-    /// # std::fs::remove_file("my_old_db");
-    /// # std::fs::remove_file("my_new_db");
+    /// # let _ = std::fs::remove_dir_all("my_old_db");
+    /// # let _ = std::fs::remove_dir_all("my_new_db");
     /// # Ok(()) }
     /// ```
     pub fn import(
@@ -421,11 +472,96 @@ impl Db {
         }
     }
 
+    /// Spawns a thread that sends every key and value of every
+    /// `Tree` in the `Db`, along with the name of its `Tree`,
+    /// into a channel that holds at most `buffer` items. This
+    /// lets a slow consumer, like a backup sent over the
+    /// network, read the export at its own pace.
+    ///
+    /// The export is a consistent point-in-time view of the
+    /// whole `Db`: a [`Snapshot`] of each `Tree` is taken before
+    /// this returns, with writes to every `Tree` blocked until
+    /// all of them are taken, and the thread then reads only
+    /// from those snapshots. Trees are sent one after another,
+    /// ordered by name, and each in key order. The thread stops
+    /// as soon as the receiver is dropped. See [`Snapshot`] for
+    /// the memory that is kept alive until the export is done.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let tree = db.open_tree(b"tree")?;
+    /// for i in 0..100_u8 {
+    ///     tree.insert(&[i], vec![i])?;
+    /// }
+    ///
+    /// let (handle, rx) = db.export_streaming(16)?;
+    ///
+    /// // writes after the call are not part of the export
+    /// tree.insert(&[100], vec![100])?;
+    ///
+    /// let mut count = 0;
+    /// for (name, k, v) in rx {
+    ///     assert_eq!(name, b"tree");
+    ///     assert_eq!(k, v);
+    ///     count += 1;
+    /// }
+    /// handle.join().unwrap();
+    /// assert_eq!(count, 100);
+    /// # Ok(()) }
+    /// ```
+    pub fn export_streaming(
+        &self,
+        buffer: usize,
+    ) -> Result<(std::thread::JoinHandle<()>, ExportReceiver)> {
+        // the tenants lock is released before blocking writers,
+        // since opening a tree may wait for both
+        let mut trees: Vec<(IVec, Tree)> = self
+            .tenants
+            .read()
+            .iter()
+            .map(|(name, tree)| (name.clone(), tree.clone()))
+            .collect();
+        trees.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+
+        let snapshots = {
+            let _cc = concurrency_control::write();
+            let guard = pin();
+            trees
+                .into_iter()
+                .map(|(name, tree)| Ok((name, tree.snapshot_inner(&guard)?)))
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        let (tx, rx) = std::sync::mpsc::sync_channel(buffer);
+
+        let handle = std::thread::Builder::new()
+            .name("sled-export".into())
+            .spawn(move || {
+                for (name, snapshot) in snapshots {
+                    for (k, v) in snapshot.iter() {
+                        if tx.send((name.clone(), k, v)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            })?;
+
+        Ok((handle, rx))
+    }
+
     /// Returns the CRC32 of all keys and values
     /// in this Db.
     ///
     /// This is O(N) and locks all underlying Trees
-    /// for the duration of the entire scan.
+    /// for the duration of the entire scan. Each tree is
+    /// split into parts that are hashed in parallel on the
+    /// background threadpool, and the results are combined
+    /// in key order, so the checksum is the same as hashing
+    /// every tree serially.
     pub fn checksum(&self) -> Result<u32> {
         let tenants_mu = self.tenants.write();
 
@@ -438,13 +574,7 @@ impl Db {
 
         for (name, tree) in &tenants {
             hasher.update(name);
-
-            let mut iter = tree.iter();
-            while let Some(kv_res) = iter.next_inner() {
-                let (k, v) = kv_res?;
-                hasher.update(&k);
-                hasher.update(&v);
-            }
+            hasher.combine(&tree.parallel_hasher()?);
         }
 
         Ok(hasher.finalize())
@@ -456,15 +586,280 @@ impl Db {
         self.context.pagecache.size_on_disk()
     }
 
-    /// Traverses all files and calculates their total physical
-    /// size, then traverses all pages and calculates their
-    /// total logical size, then divides the physical size
-    /// by the logical size.
-    #[doc(hidden)]
+    /// Preallocates at least `bytes` of heap space for values of
+    /// roughly `value_len` bytes, so that a large import does not
+    /// have to grow the heap files while it runs. Returns the
+    /// number of bytes that were preallocated.
+    ///
+    /// Only values that are too large to be stored inline in the
+    /// log, which is anything over about 30kb, are written to the
+    /// heap. The heap keeps a separate file for each power-of-two
+    /// size class, so the space is only used by values that fall
+    /// into the same class as `value_len`. Preallocated space
+    /// that is still unused when the database is reopened is
+    /// released again.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let value = vec![7; 100 * 1024];
+    /// let preallocated = db.preallocate_heap(value.len() as u64, 1 << 20)?;
+    /// assert!(preallocated >= 1 << 20);
+    ///
+    /// db.insert(b"big", value.clone())?;
+    /// assert_eq!(db.get(b"big")?, Some(value.into()));
+    /// # Ok(()) }
+    /// ```
+    #[doc(alias = "reserve_space")]
+    #[doc(alias = "preallocate")]
+    pub fn preallocate_heap(&self, value_len: u64, bytes: u64) -> Result<u64> {
+        // heap items carry a 13 byte header
+        self.context.heap.preallocate(value_len + 13, bytes)
+    }
+
+    /// Returns the page cache hit, miss, and eviction counters
+    /// accumulated since this `Db` was opened, along with an
+    /// estimate of the bytes currently resident in the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", b"1")?;
+    /// db.get(b"a")?;
+    ///
+    /// let stats = db.cache_stats();
+    /// assert!(stats.hits > 0);
+    /// # Ok(()) }
+    /// ```
+    pub fn cache_stats(&self) -> CacheStats {
+        self.context.pagecache.cache_stats()
+    }
+
+    /// Resumes writing after a transient error, such as a full
+    /// disk, once its cause has been fixed.
+    ///
+    /// When writing to the log fails, the error is stored and
+    /// returned by every following write and flush, so that
+    /// nothing is written after a hole in the log. If the
+    /// error is transient (see [`Error::is_transient`]), this
+    /// writes out the buffers that failed again and, if that
+    /// succeeds, clears the stored error. Writes that returned
+    /// the error may still have been made durable by this.
+    ///
+    /// Returns `Ok(())` if there was no stored error. Fatal
+    /// errors, like detected corruption or a failed `fsync`,
+    /// cannot be cleared and are returned as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// if let Err(e) = db.flush() {
+    ///     if e.is_transient() {
+    ///         // after freeing up disk space:
+    ///         db.clear_transient_error()?;
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn clear_transient_error(&self) -> Result<()> {
+        self.context.pagecache.log.iobufs.clear_transient_error()
+    }
+
+    /// Returns how much of the log was replayed while this
+    /// `Db` was opened, and how long recovery took. These
+    /// are the same values last passed to the
+    /// `Config::recovery_progress` callback.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// // a new database has no log to replay
+    /// assert_eq!(db.recovery_stats().log_entries, 0);
+    /// # Ok(()) }
+    /// ```
+    pub fn recovery_stats(&self) -> RecoveryProgress {
+        self.context.pagecache.recovery_stats()
+    }
+
+    /// Returns the latencies of reads, writes and flushes along
+    /// with the page cache counters of this `Db`, which can be
+    /// exported to Prometheus with
+    /// [`MetricsSnapshot::encode_prometheus`]. The latencies
+    /// cover every `Db` in the process.
+    ///
+    /// Requires the `metrics` feature to be enabled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", b"1")?;
+    /// db.get(b"a")?;
+    ///
+    /// let metrics = db.metrics_snapshot();
+    /// assert!(metrics.insert.count > 0);
+    /// assert!(metrics.encode_prometheus().contains("op=\"get\""));
+    /// # Ok(()) }
+    /// ```
+    #[cfg(feature = "metrics")]
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot::new(self.cache_stats())
+    }
+
+    /// Summarizes the nodes of every tree in this `Db`: how many
+    /// are resident in memory or paged out, and a histogram of
+    /// the in-memory sizes of the resident ones. This only reads
+    /// the page table, so nodes that are paged out are counted
+    /// but not read back from disk to be measured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", vec![0; 100])?;
+    ///
+    /// let stats = db.node_stats();
+    /// assert!(stats.resident > 0);
+    /// assert_eq!(stats.size_histogram.iter().sum::<u64>(), stats.resident);
+    /// assert!(stats.resident_bytes > 100);
+    /// # Ok(()) }
+    /// ```
+    pub fn node_stats(&self) -> NodeStats {
+        self.context.pagecache.node_stats()
+    }
+
+    /// Pages out every node of every tree in this `Db`, dropping
+    /// them from memory so that each one is read back from disk
+    /// the next time it is used. Returns the number of nodes
+    /// that were paged out. This can be used to release memory,
+    /// for example before forking, or to exercise the read path
+    /// in tests. Use [`Tree::page_out`] for a single key range.
+    ///
+    /// Writes that have not been flushed yet are made durable
+    /// before the nodes holding them are read back, so nothing
+    /// is lost, but the first accesses afterwards will be slow.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", vec![0; 100])?;
+    ///
+    /// assert!(db.page_out_all()? > 0);
+    /// assert_eq!(db.get(b"a")?, Some(vec![0; 100].into()));
+    /// # Ok(()) }
+    /// ```
+    pub fn page_out_all(&self) -> Result<usize> {
+        let _cc = concurrency_control::read();
+        self.context.pagecache.page_out_all()
+    }
+
+    /// Moves live pages out of fragmented log segments and then
+    /// flushes, so that those segments can be reused for new
+    /// writes. This is the same cleaning work that happens a
+    /// little at a time during normal writes and in the
+    /// background flusher, run for everything currently queued.
+    /// Returns the number of pages that were moved.
+    ///
+    /// Cleaned segments are recycled rather than returned to
+    /// the operating system, so `size_on_disk` will usually not
+    /// shrink right away. Instead, subsequent writes fill the
+    /// reclaimed space before the storage files grow again.
+    ///
+    /// This may be called concurrently with reads and writes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..1000_u32 {
+    ///     db.insert(i.to_be_bytes(), vec![0; 64])?;
+    /// }
+    /// for i in 0..900_u32 {
+    ///     db.remove(i.to_be_bytes())?;
+    /// }
+    ///
+    /// db.compact()?;
+    /// assert_eq!(db.len(), 100);
+    /// # Ok(()) }
+    /// ```
+    pub fn compact(&self) -> Result<usize> {
+        self.flush()?;
+
+        #[cfg(not(miri))]
+        let moved = self.context.pagecache.gc_all()?;
+
+        #[cfg(miri)]
+        let moved = 0;
+
+        debug!("compaction moved {} pages", moved);
+
+        self.flush()?;
+
+        Ok(moved)
+    }
+
+    /// Returns how many times larger the storage files are than
+    /// the data they hold: [`Db::size_on_disk`] divided by the
+    /// in-memory size of every live node plus one segment. The
+    /// segment is added so that a nearly empty database does not
+    /// report an enormous ratio. This traverses every node, and
+    /// pulls nodes that were paged out back into memory.
+    ///
+    /// A value that keeps growing under a steady workload means
+    /// the log holds more and more stale data, which
+    /// [`Db::compact`] can reclaim.
     pub fn space_amplification(&self) -> Result<f64> {
         self.context.pagecache.space_amplification()
     }
 
+    /// Returns the bytes written to the log and the heap since
+    /// this `Db` was opened, divided by the key and value bytes
+    /// written by tree operations in that time. Removals count
+    /// their key bytes. This includes the cost of consolidating
+    /// nodes and of cleaning segments, and is 0.0 if nothing
+    /// has been written yet.
+    ///
+    /// Bytes are only counted once their buffer is written out,
+    /// so this is most meaningful after a flush.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// assert_eq!(db.write_amplification(), 0.0);
+    ///
+    /// db.insert(b"key", vec![0; 1024])?;
+    /// db.flush()?;
+    /// assert!(db.write_amplification() >= 1.0);
+    /// # Ok(()) }
+    /// ```
+    pub fn write_amplification(&self) -> f64 {
+        self.context.pagecache.write_amplification()
+    }
+
     /// Returns a true value if one of the tree names linked
     /// to the database is found, if not a false will be returned.
     pub fn contains_tree<V: AsRef<[u8]>>(&self, name: V) -> bool {
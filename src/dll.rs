@@ -222,6 +222,20 @@ impl DoublyLinkedList {
         }
     }
 
+    /// Returns every item, starting from the tail, which is
+    /// the least recently promoted end of the list.
+    pub(crate) fn coldest_first(&self) -> Vec<CacheAccess> {
+        let mut ret = Vec::with_capacity(self.len);
+        let mut cursor = self.tail;
+        while !cursor.is_null() {
+            unsafe {
+                ret.push(**cursor);
+                cursor = (*cursor).next;
+            }
+        }
+        ret
+    }
+
     #[cfg(test)]
     pub(crate) fn into_vec(mut self) -> Vec<CacheAccess> {
         let mut res = vec![];
@@ -5,6 +5,10 @@ use parking_lot::{Condvar, Mutex};
 
 use super::*;
 
+// the longest the flusher waits between attempts to flush
+// while a transient error is set
+const MAX_ERROR_BACKOFF: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Clone, Copy)]
 pub(crate) enum ShutdownState {
     Running,
@@ -33,6 +37,7 @@ impl Flusher {
     /// Spawns a thread that periodically calls `callback` until dropped.
     pub(crate) fn new(
         name: String,
+        stack_size: Option<usize>,
         pagecache: PageCache,
         flush_every_ms: u64,
     ) -> Self {
@@ -40,8 +45,12 @@ impl Flusher {
         let shutdown = Arc::new(Mutex::new(ShutdownState::Running));
         let sc = Arc::new(Condvar::new());
 
-        let join_handle = thread::Builder::new()
-            .name(name)
+        let mut builder = thread::Builder::new().name(name);
+        if let Some(stack_size) = stack_size {
+            builder = builder.stack_size(stack_size);
+        }
+
+        let join_handle = builder
             .spawn({
                 let shutdown2 = shutdown.clone();
                 let sc2 = sc.clone();
@@ -62,17 +71,20 @@ fn run(
     let flush_every = Duration::from_millis(flush_every_ms);
     let mut shutdown = shutdown_mu.lock();
     let mut wrote_data = false;
+    let mut error_backoff = flush_every;
     while shutdown.is_running() || wrote_data {
         let before = std::time::Instant::now();
         let cc = concurrency_control::read();
         match pagecache.log.roll_iobuf() {
             Ok(0) => {
+                error_backoff = flush_every;
                 wrote_data = false;
                 if !shutdown.is_running() {
                     break;
                 }
             }
             Ok(_) => {
+                error_backoff = flush_every;
                 wrote_data = true;
                 if !shutdown.is_running() {
                     // loop right away if we're in
@@ -81,6 +93,24 @@ fn run(
                     continue;
                 }
             }
+            Err(e) if e.is_transient() && shutdown.is_running() => {
+                // keep the thread alive so that flushing resumes
+                // once `Db::clear_transient_error` succeeds, but
+                // retry less and less often in the meantime.
+                error!(
+                    "failed to flush from periodic flush thread, \
+                     retrying in {:?}: {}",
+                    error_backoff, e
+                );
+
+                pagecache.log.iobufs.set_global_error(e);
+
+                wrote_data = false;
+                drop(cc);
+                sc.wait_for(&mut shutdown, error_backoff);
+                error_backoff = (error_backoff * 2).min(MAX_ERROR_BACKOFF);
+                continue;
+            }
             Err(e) => {
                 error!("failed to flush from periodic flush thread: {}", e);
 
@@ -107,6 +137,15 @@ fn run(
         // where the conditional is the full body
         while {
             let made_progress = match pagecache.attempt_gc() {
+                Err(e) if e.is_transient() => {
+                    error!(
+                        "failed to clean file from periodic flush thread: {}",
+                        e
+                    );
+
+                    pagecache.log.iobufs.set_global_error(e);
+                    false
+                }
                 Err(e) => {
                     error!(
                         "failed to clean file from periodic flush thread: {}",
@@ -33,6 +33,76 @@ macro_rules! iter_try {
     };
 }
 
+pub(crate) type CursorPage = Result<(Vec<(IVec, IVec)>, Option<Cursor>)>;
+
+/// A resumable position within a range, returned by
+/// [`Iter::take_cursor`] and consumed by
+/// [`Tree::range_from_cursor`]. It records the next key
+/// to return and the end of the original range, so it can
+/// be stored with [`Cursor::to_bytes`] and used again after
+/// the `Db` is reopened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub(crate) next: IVec,
+    pub(crate) hi: Bound<IVec>,
+}
+
+impl Cursor {
+    /// The key that the next page will start at.
+    pub fn next_key(&self) -> &[u8] {
+        &self.next
+    }
+
+    /// Encodes the cursor so that it can be handed to a
+    /// client or persisted outside of sled.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let (tag, hi): (u8, &[u8]) = match &self.hi {
+            Bound::Unbounded => (0, &[]),
+            Bound::Included(hi) => (1, hi),
+            Bound::Excluded(hi) => (2, hi),
+        };
+
+        let next_len = u64::try_from(self.next.len()).unwrap();
+
+        let mut ret = Vec::with_capacity(9 + self.next.len() + hi.len());
+        ret.push(tag);
+        ret.extend_from_slice(&next_len.to_le_bytes());
+        ret.extend_from_slice(&self.next);
+        ret.extend_from_slice(hi);
+        ret
+    }
+
+    /// Decodes a cursor previously encoded with
+    /// [`Cursor::to_bytes`], returning `None` if the
+    /// bytes are not a valid cursor.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Cursor> {
+        if bytes.len() < 9 {
+            return None;
+        }
+
+        let (tag, rest) = bytes.split_at(1);
+        let (next_len_buf, rest) = rest.split_at(8);
+        let mut next_len = [0; 8];
+        next_len.copy_from_slice(next_len_buf);
+        let next_len = usize::try_from(u64::from_le_bytes(next_len)).ok()?;
+
+        if rest.len() < next_len {
+            return None;
+        }
+
+        let (next, hi) = rest.split_at(next_len);
+
+        let hi = match tag[0] {
+            0 if hi.is_empty() => Bound::Unbounded,
+            1 => Bound::Included(hi.into()),
+            2 => Bound::Excluded(hi.into()),
+            _ => return None,
+        };
+
+        Some(Cursor { next: next.into(), hi })
+    }
+}
+
 /// An iterator over keys and values in a `Tree`.
 pub struct Iter {
     pub(super) tree: Tree,
@@ -40,23 +110,78 @@ pub struct Iter {
     pub(super) lo: Bound<IVec>,
     pub(super) cached_node: Option<(PageId, Node)>,
     pub(super) going_forward: bool,
+    pub(super) cold: bool,
+    // values are left empty when set, by `Iter::keys`
+    pub(super) keys_only: bool,
 }
 
 impl Iter {
-    /// Iterate over the keys of this Tree
+    /// Iterate over the keys of this Tree. Values are not
+    /// copied out of the tree at all, which makes this cheaper
+    /// than mapping over the items when values are large.
     pub fn keys(
-        self,
+        mut self,
     ) -> impl DoubleEndedIterator<Item = Result<IVec>> + Send + Sync {
+        self.keys_only = true;
         self.map(|r| r.map(|(k, _v)| k))
     }
 
-    /// Iterate over the values of this Tree
+    /// Iterate over the values of this Tree. Keys are still
+    /// read, since the iterator resumes from the last key it
+    /// returned.
     pub fn values(
         self,
     ) -> impl DoubleEndedIterator<Item = Result<IVec>> + Send + Sync {
         self.map(|r| r.map(|(_k, v)| v))
     }
 
+    /// Returns up to `n` items from the front of the iterator,
+    /// along with a [`Cursor`] to fetch the following items
+    /// with [`Tree::range_from_cursor`], or `None` if there
+    /// are no items left in the range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..5_u8 {
+    ///     db.insert(&[i], vec![i])?;
+    /// }
+    ///
+    /// let end: &[u8] = &[4];
+    /// let (page, cursor) = db.range(..end).take_cursor(2)?;
+    /// assert_eq!(page.len(), 2);
+    ///
+    /// let cursor = sled::Cursor::from_bytes(&cursor.unwrap().to_bytes());
+    /// let (page, cursor) = db.range_from_cursor(&cursor.unwrap(), 2)?;
+    /// assert_eq!(page[0].0, [2]);
+    /// assert_eq!(page[1].0, [3]);
+    /// assert!(cursor.is_none());
+    /// # Ok(()) }
+    /// ```
+    pub fn take_cursor(mut self, n: usize) -> CursorPage {
+        let mut page = Vec::with_capacity(n);
+
+        while page.len() < n {
+            match self.next() {
+                Some(item) => page.push(item?),
+                None => return Ok((page, None)),
+            }
+        }
+
+        let cursor = match self.next() {
+            Some(item) => {
+                let (next, _) = item?;
+                Some(Cursor { next, hi: self.hi })
+            }
+            None => None,
+        };
+
+        Ok((page, cursor))
+    }
+
     fn bounds_collapsed(&self) -> bool {
         match (&self.lo, &self.hi) {
             (Bound::Included(ref start), Bound::Included(ref end))
@@ -84,6 +209,14 @@ impl Iter {
         }
     }
 
+    fn view_for_key<'g, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        guard: &'g Guard,
+    ) -> Result<tree::View<'g>> {
+        self.tree.view_for_key_inner(key, self.cold, guard)
+    }
+
     pub(crate) fn next_inner(&mut self) -> Option<<Self as Iterator>::Item> {
         let guard = pin();
         let (mut pid, mut node) = if let (true, Some((pid, node))) =
@@ -91,8 +224,7 @@ impl Iter {
         {
             (pid, node)
         } else {
-            let view =
-                iter_try!(self.tree.view_for_key(self.low_key(), &guard));
+            let view = iter_try!(self.view_for_key(self.low_key(), &guard));
             (view.pid, view.deref().clone())
         };
 
@@ -103,8 +235,7 @@ impl Iter {
 
             if !node.contains_upper_bound(&self.lo) {
                 // node too low (maybe merged, maybe exhausted?)
-                let view =
-                    iter_try!(self.tree.view_for_key(self.low_key(), &guard));
+                let view = iter_try!(self.view_for_key(self.low_key(), &guard));
 
                 pid = view.pid;
                 node = view.deref().clone();
@@ -112,13 +243,15 @@ impl Iter {
             } else if !node.contains_lower_bound(&self.lo, true) {
                 // node too high (maybe split, maybe exhausted?)
                 let seek_key = possible_predecessor(node.lo())?;
-                let view = iter_try!(self.tree.view_for_key(seek_key, &guard));
+                let view = iter_try!(self.view_for_key(seek_key, &guard));
                 pid = view.pid;
                 node = view.deref().clone();
                 continue;
             }
 
-            if let Some((key, value)) = node.successor(&self.lo) {
+            if let Some((key, value)) =
+                node.successor_with(&self.lo, !self.keys_only)
+            {
                 self.lo = Bound::Excluded(key.clone());
                 self.cached_node = Some((pid, node));
                 self.going_forward = true;
@@ -133,6 +266,10 @@ impl Iter {
                     }
                     _ => return None,
                 }
+            } else if node.contains_upper_bound(&self.hi) {
+                // the rest of the range would have been in this
+                // node, so avoid paging in its right sibling.
+                return None;
             } else if let Some(hi) = node.hi() {
                 self.lo = Bound::Included(hi.into());
                 continue;
@@ -160,6 +297,51 @@ impl Iterator for Iter {
     fn last(mut self) -> Option<Self::Item> {
         self.next_back()
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.bounds_collapsed() {
+            return (0, Some(0));
+        }
+        match &self.cached_node {
+            Some((_, node)) => (node.count_in_range(&self.lo, &self.hi), None),
+            None => (0, None),
+        }
+    }
+
+    /// Counts the remaining items by counting the keys of each
+    /// node in range, without copying any keys or values out
+    /// of the tree.
+    fn count(mut self) -> usize {
+        let mut count = 0;
+
+        for _ in 0..MAX_LOOPS {
+            if self.bounds_collapsed() {
+                return count;
+            }
+
+            let _cc = concurrency_control::read();
+            let guard = pin();
+            let view = match self.view_for_key(self.low_key(), &guard) {
+                Ok(view) => view,
+                // the error would have been yielded as an item
+                Err(_) => return count + 1,
+            };
+
+            count += view.count_in_range(&self.lo, &self.hi);
+
+            if view.contains_upper_bound(&self.hi) {
+                return count;
+            } else if let Some(hi) = view.hi() {
+                self.lo = Bound::Included(hi.into());
+            } else {
+                return count;
+            }
+        }
+        panic!(
+            "fucked up tree traversal count({:?}) on {:?}",
+            self.lo, self.tree
+        );
+    }
 }
 
 impl DoubleEndedIterator for Iter {
@@ -174,8 +356,7 @@ impl DoubleEndedIterator for Iter {
         {
             (pid, node)
         } else {
-            let view =
-                iter_try!(self.tree.view_for_key(self.high_key(), &guard));
+            let view = iter_try!(self.view_for_key(self.high_key(), &guard));
             (view.pid, view.deref().clone())
         };
 
@@ -186,8 +367,7 @@ impl DoubleEndedIterator for Iter {
 
             if !node.contains_upper_bound(&self.hi) {
                 // node too low (maybe merged, maybe exhausted?)
-                let view =
-                    iter_try!(self.tree.view_for_key(self.high_key(), &guard));
+                let view = iter_try!(self.view_for_key(self.high_key(), &guard));
 
                 pid = view.pid;
                 node = view.deref().clone();
@@ -195,13 +375,15 @@ impl DoubleEndedIterator for Iter {
             } else if !node.contains_lower_bound(&self.hi, false) {
                 // node too high (maybe split, maybe exhausted?)
                 let seek_key = possible_predecessor(node.lo())?;
-                let view = iter_try!(self.tree.view_for_key(seek_key, &guard));
+                let view = iter_try!(self.view_for_key(seek_key, &guard));
                 pid = view.pid;
                 node = view.deref().clone();
                 continue;
             }
 
-            if let Some((key, value)) = node.predecessor(&self.hi) {
+            if let Some((key, value)) =
+                node.predecessor_with(&self.hi, !self.keys_only)
+            {
                 self.hi = Bound::Excluded(key.clone());
                 self.cached_node = Some((pid, node));
                 self.going_forward = false;
@@ -216,7 +398,9 @@ impl DoubleEndedIterator for Iter {
                     }
                     _ => return None,
                 }
-            } else if node.lo().is_empty() {
+            } else if node.contains_lower_bound(&self.lo, true) {
+                // the rest of the range would have been in this
+                // node, so avoid paging in its left sibling.
                 return None;
             } else {
                 self.hi = Bound::Excluded(node.lo().into());
@@ -117,6 +117,34 @@ impl Hash for IVec {
 }
 
 impl IVec {
+    /// The largest number of bytes that an `IVec` stores
+    /// inline, without a separate heap allocation. This is
+    /// one less than the size of a pointer, so 7 bytes on
+    /// 64-bit platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use sled::IVec;
+    ///
+    /// let small = IVec::from(vec![0; IVec::inline_capacity()]);
+    /// assert!(small.is_inlined());
+    ///
+    /// let large = IVec::from(vec![0; IVec::inline_capacity() + 1]);
+    /// assert!(!large.is_inlined());
+    /// ```
+    pub const fn inline_capacity() -> usize {
+        CUTOFF
+    }
+
+    /// Returns `true` if the bytes are stored inline rather
+    /// than in a separate heap allocation. This can be used
+    /// to measure how often keys or values spill to the
+    /// heap, see [`IVec::inline_capacity`].
+    pub fn is_inlined(&self) -> bool {
+        self.is_inline()
+    }
+
     fn new(slice: &[u8]) -> Self {
         let mut data = [0_u8; SZ];
         if slice.len() <= CUTOFF {
@@ -323,6 +351,14 @@ mod qc {
         assert_eq!(iv2, vec![4; 128]);
     }
 
+    #[test]
+    fn ivec_inline_capacity() {
+        let cap = IVec::inline_capacity();
+        assert!(IVec::from(vec![1; cap]).is_inlined());
+        assert!(!IVec::from(vec![1; cap + 1]).is_inlined());
+        assert!(IVec::default().is_inlined());
+    }
+
     #[test]
     fn ivec_as_mut_identity() {
         let initial = &[1];
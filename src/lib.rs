@@ -182,6 +182,8 @@ macro_rules! testing_assert {
     };
 }
 
+#[cfg(feature = "async")]
+mod async_db;
 mod atomic_shim;
 mod backoff;
 mod batch;
@@ -208,6 +210,7 @@ mod oneshot;
 mod pagecache;
 mod result;
 mod serialization;
+mod snapshot;
 mod stack;
 mod subscriber;
 mod sys_limits;
@@ -230,6 +233,9 @@ mod flusher;
 /// The event log helps debug concurrency issues.
 pub mod event_log;
 
+#[cfg(feature = "async")]
+pub use self::async_db::{AsyncDb, DbFuture};
+
 /// Opens a `Db` with a default configuration at the
 /// specified path. This will create a new storage
 /// directory at the specified path if it does
@@ -274,17 +280,25 @@ pub use self::{
 };
 
 pub use self::{
-    batch::Batch,
-    config::{Config, Mode},
+    batch::{Batch, ConditionalBatch},
+    config::{Config, Mode, SplitPolicy, WarmPolicy},
+    pagecache::{CacheStats, NodeStats, RecoveryProgress},
     db::Db,
-    iter::Iter,
+    iter::{Cursor, Iter},
     ivec::IVec,
     result::{Error, Result},
+    snapshot::Snapshot,
     subscriber::{Event, Subscriber},
     transaction::Transactional,
-    tree::{CompareAndSwapError, Tree},
+    tree::{
+        CasOutcome, Checkpoint, CompareAndSwapError, Corruption, FlushHandle,
+        FlushResult, NodeInfo, Tree,
+    },
 };
 
+#[cfg(feature = "metrics")]
+pub use self::metrics::{LatencyStats, MetricsSnapshot};
+
 #[cfg(feature = "metrics")]
 use self::{
     histogram::Histogram,
@@ -508,6 +522,8 @@ mod compile_time_assertions {
         _assert_send_sync::<IVec>();
         _assert_send_sync::<Config>();
         _assert_send_sync::<CompareAndSwapError>();
+        _assert_send_sync::<CasOutcome>();
+        _assert_send_sync::<FlushHandle>();
         _assert_send_sync::<Error>();
         _assert_send_sync::<Event>();
         _assert_send_sync::<Mode>();
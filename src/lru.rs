@@ -253,6 +253,7 @@ impl CacheAccess {
 /// A simple LRU cache.
 pub struct Lru {
     shards: Vec<(AccessQueue, FastLock<Shard>)>,
+    resident: AtomicUsize,
 }
 
 impl Lru {
@@ -270,7 +271,13 @@ impl Lru {
             (AccessQueue::default(), FastLock::new(Shard::new(shard_capacity)))
         });
 
-        Self { shards }
+        Self { shards, resident: AtomicUsize::new(0) }
+    }
+
+    /// Returns the sum of the (rounded-up) sizes of the
+    /// items currently tracked across all shards.
+    pub(crate) fn resident_bytes(&self) -> u64 {
+        self.resident.load(Ordering::Relaxed) as u64
     }
 
     /// Called when an item is accessed. Returns a Vec of items to be
@@ -302,6 +309,7 @@ impl Lru {
             // only try to acquire this if the access queue has filled
             // an entire segment
             if let Some(mut shard) = shard_mu.try_lock() {
+                let size_before = shard.size;
                 let accesses = access_queue.take(guard);
                 for item in accesses {
                     let to_evict = shard.accessed(item);
@@ -312,6 +320,49 @@ impl Lru {
                         ret.push(address);
                     }
                 }
+
+                let size_after = shard.size;
+                if size_after >= size_before {
+                    self.resident
+                        .fetch_add(size_after - size_before, Ordering::Relaxed);
+                } else {
+                    self.resident
+                        .fetch_sub(size_before - size_after, Ordering::Relaxed);
+                }
+            }
+        }
+        ret
+    }
+
+    /// Returns the ids of the tracked items, approximately
+    /// ordered from least to most recently accessed. Each
+    /// shard keeps its own order, so the shards are merged
+    /// by rank rather than by a global access time, and
+    /// accesses still sitting in a shard's access queue are
+    /// not yet reflected.
+    pub(crate) fn coldest(&self) -> Vec<PageId> {
+        const SHARD_BITS: usize = N_SHARDS.trailing_zeros() as usize;
+
+        let mut per_shard = Vec::with_capacity(N_SHARDS);
+        for (_, shard_mu) in &self.shards {
+            let shard = loop {
+                if let Some(shard) = shard_mu.try_lock() {
+                    break shard;
+                }
+                std::hint::spin_loop();
+            };
+            per_shard.push(shard.dll.coldest_first());
+        }
+
+        let mut ret = vec![];
+        let max_len = per_shard.iter().map(Vec::len).max().unwrap_or(0);
+        for rank in 0..max_len {
+            for (shard_idx, items) in per_shard.iter().enumerate() {
+                if let Some(item) = items.get(rank) {
+                    let address = (PageId::from(item.pid) << SHARD_BITS)
+                        + shard_idx as PageId;
+                    ret.push(address);
+                }
             }
         }
         ret
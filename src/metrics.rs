@@ -131,6 +131,7 @@ pub struct Metrics {
     pub tree_child_split_attempt: CachePadded<AtomicUsize>,
     pub tree_child_split_success: CachePadded<AtomicUsize>,
     pub tree_del: Histogram,
+    pub tree_flush: Histogram,
     pub tree_get: Histogram,
     pub tree_loops: CachePadded<AtomicUsize>,
     pub tree_merge: Histogram,
@@ -265,6 +266,7 @@ impl Metrics {
             lat("merge", &self.tree_merge),
             lat("del", &self.tree_del),
             lat("cas", &self.tree_cas),
+            lat("flush", &self.tree_flush),
             lat("scan", &self.tree_scan),
             lat("rev scan", &self.tree_reverse_scan),
         ]));
@@ -414,3 +416,159 @@ impl Metrics {
         ret
     }
 }
+
+/// Latency percentiles for one kind of operation, in
+/// microseconds, as part of a [`MetricsSnapshot`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct LatencyStats {
+    /// The number of measured operations.
+    pub count: u64,
+    /// The total time spent in the measured operations.
+    pub sum_us: f64,
+    /// The median latency.
+    pub p50_us: f64,
+    /// The 90th percentile latency.
+    pub p90_us: f64,
+    /// The 99th percentile latency.
+    pub p99_us: f64,
+    /// The highest recorded latency.
+    pub max_us: f64,
+}
+
+impl LatencyStats {
+    fn from_histogram(histo: &Histogram) -> LatencyStats {
+        LatencyStats {
+            count: histo.count() as u64,
+            sum_us: histo.sum() as f64 / 1e3,
+            p50_us: histo.percentile(50.) / 1e3,
+            p90_us: histo.percentile(90.) / 1e3,
+            p99_us: histo.percentile(99.) / 1e3,
+            max_us: histo.percentile(100.) / 1e3,
+        }
+    }
+}
+
+/// Operation latencies and cache counters, returned by
+/// `Db::metrics_snapshot`.
+///
+/// The latencies are collected for every `Db` in the
+/// process, like the ones printed by [`print_profile`],
+/// while the cache counters belong to a single `Db`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Point reads.
+    pub get: LatencyStats,
+    /// Inserts, including those applied by batches and
+    /// transactions.
+    pub insert: LatencyStats,
+    /// Removals, including those applied by batches and
+    /// transactions.
+    pub remove: LatencyStats,
+    /// Compare and swap operations.
+    pub compare_and_swap: LatencyStats,
+    /// Merges.
+    pub merge: LatencyStats,
+    /// Synchronous flushes.
+    pub flush: LatencyStats,
+    /// The page cache counters of the `Db`.
+    pub cache: CacheStats,
+}
+
+impl MetricsSnapshot {
+    pub(crate) fn new(cache: CacheStats) -> MetricsSnapshot {
+        MetricsSnapshot {
+            get: LatencyStats::from_histogram(&M.tree_get),
+            insert: LatencyStats::from_histogram(&M.tree_set),
+            remove: LatencyStats::from_histogram(&M.tree_del),
+            compare_and_swap: LatencyStats::from_histogram(&M.tree_cas),
+            merge: LatencyStats::from_histogram(&M.tree_merge),
+            flush: LatencyStats::from_histogram(&M.tree_flush),
+            cache,
+        }
+    }
+
+    /// The fraction of page reads served from memory, between
+    /// 0.0 and 1.0, or 0.0 if nothing has been read yet.
+    pub fn cache_hit_ratio(&self) -> f64 {
+        let reads = self.cache.hits + self.cache.misses;
+        if reads == 0 {
+            0.
+        } else {
+            self.cache.hits as f64 / reads as f64
+        }
+    }
+
+    /// Encodes the snapshot in the Prometheus text exposition
+    /// format. Latencies are exported as a summary named
+    /// `sled_op_latency_microseconds` with an `op` label, and
+    /// the cache counters as `sled_cache_*` metrics.
+    pub fn encode_prometheus(&self) -> String {
+        let mut ret = String::new();
+
+        ret.push_str(
+            "# HELP sled_op_latency_microseconds \
+             latency of sled operations\n\
+             # TYPE sled_op_latency_microseconds summary\n",
+        );
+        for (op, stats) in [
+            ("get", &self.get),
+            ("insert", &self.insert),
+            ("remove", &self.remove),
+            ("compare_and_swap", &self.compare_and_swap),
+            ("merge", &self.merge),
+            ("flush", &self.flush),
+        ] {
+            for (quantile, value) in [
+                ("0.5", stats.p50_us),
+                ("0.9", stats.p90_us),
+                ("0.99", stats.p99_us),
+                ("1", stats.max_us),
+            ] {
+                ret.push_str(&format!(
+                    "sled_op_latency_microseconds\
+                     {{op=\"{}\",quantile=\"{}\"}} {}\n",
+                    op, quantile, value
+                ));
+            }
+            ret.push_str(&format!(
+                "sled_op_latency_microseconds_sum{{op=\"{}\"}} {}\n\
+                 sled_op_latency_microseconds_count{{op=\"{}\"}} {}\n",
+                op, stats.sum_us, op, stats.count
+            ));
+        }
+
+        for (name, kind, help, value) in [
+            (
+                "sled_cache_hits_total",
+                "counter",
+                "page reads served from memory",
+                self.cache.hits,
+            ),
+            (
+                "sled_cache_misses_total",
+                "counter",
+                "page reads that had to be pulled from disk",
+                self.cache.misses,
+            ),
+            (
+                "sled_cache_evictions_total",
+                "counter",
+                "pages paged out of memory",
+                self.cache.evictions,
+            ),
+            (
+                "sled_cache_resident_bytes",
+                "gauge",
+                "estimated bytes of pages tracked by the cache",
+                self.cache.resident_bytes,
+            ),
+        ] {
+            ret.push_str(&format!(
+                "# HELP {0} {1}\n# TYPE {0} {2}\n{0} {3}\n",
+                name, help, kind, value
+            ));
+        }
+
+        ret
+    }
+}
@@ -10,14 +10,19 @@ use std::{
     fmt,
     mem::{align_of, size_of},
     num::{NonZeroU16, NonZeroU64},
-    ops::{Bound, Deref, DerefMut},
+    ops::{Bound, ControlFlow, Deref, DerefMut},
     sync::Arc,
 };
 
-use crate::{varint, IVec, Link};
+use crate::{varint, IVec, Link, SplitPolicy};
 
 const ALIGNMENT: usize = align_of::<Header>();
 
+// The layout version written into the header of every node.
+// `Node::from_raw` dispatches on it, so a future layout change
+// should bump this and keep a branch for reading older nodes.
+const FORMAT_VERSION: u8 = 1;
+
 macro_rules! tf {
     ($e:expr) => {
         usize::try_from($e).unwrap()
@@ -94,6 +99,14 @@ fn apply_computed_distance(mut buf: &mut [u8], mut distance: usize) {
     }
 }
 
+fn value_if(value: &[u8], with_value: bool) -> IVec {
+    if with_value {
+        value.into()
+    } else {
+        IVec::default()
+    }
+}
+
 // TODO change to u64 or u128 output
 // This function has several responsibilities:
 // * `find` will call this when looking for the
@@ -580,15 +593,58 @@ impl Node {
         }
     }
 
+    /// Calls `f` with each key and value in this node, in
+    /// ascending order, until it returns `ControlFlow::Break`.
+    /// Each key is decoded into `buf`, which is reused between
+    /// calls, so nothing is allocated per item.
+    pub(crate) fn for_each_kv<F>(
+        &self,
+        buf: &mut Vec<u8>,
+        mut f: F,
+    ) -> ControlFlow<()>
+    where
+        F: FnMut(&[u8], &[u8]) -> ControlFlow<()>,
+    {
+        let prefix = self.prefix();
+        for (key, value) in self.iter() {
+            buf.clear();
+            buf.extend_from_slice(prefix);
+            buf.resize(prefix.len() + key.len(), 0);
+            key.write_into(&mut buf[prefix.len()..]);
+            if matches!(f(buf, value), ControlFlow::Break(_)) {
+                return ControlFlow::Break(());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
     pub(crate) fn iter_index_pids(&self) -> impl '_ + Iterator<Item = u64> {
         log::trace!("iter_index_pids on node {:?}", self);
         self.iter().map(|(_, v)| u64::from_le_bytes(v.try_into().unwrap()))
     }
 
-    pub(crate) unsafe fn from_raw(buf: &[u8]) -> Node {
-        Node {
-            overlay: Default::default(),
-            inner: Arc::new(Inner::from_raw(buf)),
+    pub(crate) unsafe fn from_raw(buf: &[u8]) -> crate::Result<Node> {
+        if buf.len() < size_of::<Header>() {
+            return Err(crate::Error::corruption(None));
+        }
+
+        let inner = Inner::from_raw(buf);
+
+        match inner.version {
+            FORMAT_VERSION => {
+                Ok(Node { overlay: Default::default(), inner: Arc::new(inner) })
+            }
+            other => {
+                log::error!(
+                    "encountered node with unknown format version {}, \
+                     this build can only read version {}",
+                    other,
+                    FORMAT_VERSION
+                );
+                Err(crate::Error::Unsupported(
+                    "encountered a node written in a newer format version",
+                ))
+            }
         }
     }
 
@@ -613,6 +669,38 @@ impl Node {
         }
     }
 
+    /// Builds a node directly from sorted, unencoded items, all
+    /// of which must fall between `lo` and `hi`. Used for bulk
+    /// loading, where nodes are created already filled instead
+    /// of being grown through splits.
+    pub(crate) fn new_from_sorted(
+        lo: &[u8],
+        hi: Option<&[u8]>,
+        is_index: bool,
+        next: Option<NonZeroU64>,
+        items: &[(&[u8], &[u8])],
+    ) -> Node {
+        let prefix_len = if let Some(hi) = hi {
+            lo.iter()
+                .zip(hi.iter())
+                .take(u8::MAX as usize)
+                .take_while(|(a, b)| a == b)
+                .count()
+        } else {
+            0
+        };
+
+        let encoded: Vec<_> = items
+            .iter()
+            .map(|(k, v)| (KeyRef::Slice(&k[prefix_len..]), *v))
+            .collect();
+
+        let inner =
+            Inner::new(lo, hi, tf!(prefix_len, u8), is_index, next, &encoded);
+
+        Node { overlay: Default::default(), inner: Arc::new(inner) }
+    }
+
     pub(crate) fn apply(&self, link: &Link) -> Node {
         use self::Link::*;
 
@@ -837,8 +925,8 @@ impl Node {
         ret
     }
 
-    pub(crate) fn split(&self) -> (Node, Node) {
-        let (lhs_inner, rhs_inner) = self.merge_overlay().split();
+    pub(crate) fn split(&self, policy: SplitPolicy) -> (Node, Node) {
+        let (lhs_inner, rhs_inner) = self.merge_overlay().split(policy);
         let lhs =
             Node { inner: Arc::new(lhs_inner), overlay: Default::default() };
         let rhs =
@@ -929,6 +1017,17 @@ impl Node {
     pub(crate) fn successor(
         &self,
         bound: &Bound<IVec>,
+    ) -> Option<(IVec, IVec)> {
+        self.successor_with(bound, true)
+    }
+
+    /// Like `successor`, but the returned value is left empty
+    /// unless `with_value` is set, so that scans which only
+    /// need keys do not copy values out of the node.
+    pub(crate) fn successor_with(
+        &self,
+        bound: &Bound<IVec>,
+        with_value: bool,
     ) -> Option<(IVec, IVec)> {
         let (overlay, node_position) = match bound {
             Bound::Unbounded => (self.overlay.iter().skip(0), 0),
@@ -948,7 +1047,7 @@ impl Node {
                     Ok(idx) => {
                         return Some((
                             self.prefix_decode(self.inner.index_key(idx)),
-                            self.inner.index_value(idx).into(),
+                            value_if(self.inner.index_value(idx), with_value),
                         ))
                     }
                     Err(idx) => idx,
@@ -996,12 +1095,16 @@ impl Node {
 
         let ret: Option<(KeyRef<'_>, &[u8])> = iter.find(|(k, _)| in_bounds(k));
 
-        ret.map(|(k, v)| (self.prefix_decode(k), v.into()))
+        ret.map(|(k, v)| (self.prefix_decode(k), value_if(v, with_value)))
     }
 
-    pub(crate) fn predecessor(
+    /// Returns the last item before `bound`. The returned value
+    /// is left empty unless `with_value` is set, like in
+    /// `successor_with`.
+    pub(crate) fn predecessor_with(
         &self,
         bound: &Bound<IVec>,
+        with_value: bool,
     ) -> Option<(IVec, IVec)> {
         let (overlay, node_back_position) = match bound {
             Bound::Unbounded => (self.overlay.iter().skip(0), self.children()),
@@ -1017,7 +1120,7 @@ impl Node {
                     Ok(idx) => {
                         return Some((
                             self.prefix_decode(self.inner.index_key(idx)),
-                            self.inner.index_value(idx).into(),
+                            value_if(self.inner.index_value(idx), with_value),
                         ))
                     }
                     Err(idx) => idx,
@@ -1066,7 +1169,73 @@ impl Node {
         let ret: Option<(KeyRef<'_>, &[u8])> =
             iter.rev().find(|(k, _)| in_bounds(k));
 
-        ret.map(|(k, v)| (self.prefix_decode(k), v.into()))
+        ret.map(|(k, v)| (self.prefix_decode(k), value_if(v, with_value)))
+    }
+
+    /// Counts the keys in this node that fall between the
+    /// provided bounds without decoding or copying them.
+    pub(crate) fn count_in_range(
+        &self,
+        lo: &Bound<IVec>,
+        hi: &Bound<IVec>,
+    ) -> usize {
+        let prefix_len = self.prefix_len as usize;
+
+        // bounds that fall outside of this node either admit
+        // every key in it, in which case they are dropped, or
+        // none of them, in which case we return early.
+        let lo = match lo {
+            Bound::Included(b) | Bound::Excluded(b) if &**b >= self.lo() => {
+                if let Some(hi) = self.hi() {
+                    if &**b >= hi {
+                        return 0;
+                    }
+                }
+                let inclusive = matches!(lo, Bound::Included(_));
+                Some((&b[prefix_len..], inclusive))
+            }
+            _ => None,
+        };
+
+        let hi = match hi {
+            Bound::Included(b) | Bound::Excluded(b) => {
+                if &**b < self.lo() {
+                    return 0;
+                }
+                match self.hi() {
+                    Some(node_hi) if &**b >= node_hi => None,
+                    _ => {
+                        let inclusive = matches!(hi, Bound::Included(_));
+                        Some((&b[prefix_len..], inclusive))
+                    }
+                }
+            }
+            Bound::Unbounded => None,
+        };
+
+        self.iter()
+            .filter(|(k, _)| match lo {
+                Some((b, true)) => *k >= *b,
+                Some((b, false)) => *k > *b,
+                None => true,
+            })
+            .filter(|(k, _)| match hi {
+                Some((b, true)) => *k <= *b,
+                Some((b, false)) => *k < *b,
+                None => true,
+            })
+            .count()
+    }
+
+    /// Returns the number of keys in this node, without
+    /// visiting them unless there are unmerged writes in the
+    /// overlay.
+    pub(crate) fn key_count(&self) -> usize {
+        if self.overlay.is_empty() {
+            self.children()
+        } else {
+            self.iter().count()
+        }
     }
 
     pub(crate) fn index_next_node(&self, key: &[u8]) -> (bool, u64) {
@@ -1467,7 +1636,7 @@ impl Inner {
         header.offset_bytes = offset_bytes;
         header.children = tf!(items.len(), u32);
         header.prefix_len = prefix_len;
-        header.version = 1;
+        header.version = FORMAT_VERSION;
         header.next = next;
         header.is_index = is_index;
 
@@ -1856,12 +2025,29 @@ impl Inner {
             .max(1)
     }
 
-    fn split(&self) -> (Inner, Inner) {
+    fn split_point(&self, policy: SplitPolicy) -> usize {
+        let children = self.children();
+        let split_point = match policy {
+            SplitPolicy::Adaptive => return self.weighted_split_point(),
+            SplitPolicy::Midpoint => children / 2,
+            SplitPolicy::AppendOptimized => children - 1,
+            SplitPolicy::PrependOptimized => 1,
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_precision_loss,
+                clippy::cast_sign_loss
+            )]
+            SplitPolicy::Ratio(ratio) => (children as f32 * ratio) as usize,
+        };
+        split_point.min(children - 1).max(1)
+    }
+
+    fn split(&self, policy: SplitPolicy) -> (Inner, Inner) {
         assert!(self.children() >= 2);
         assert!(!self.merging);
         assert!(self.merging_child.is_none());
 
-        let split_point = self.weighted_split_point();
+        let split_point = self.split_point(policy);
 
         let left_max: IVec = self.index_key(split_point - 1).into();
         let right_min: IVec = self.index_key(split_point).into();
@@ -2019,6 +2205,29 @@ impl Inner {
         };
         right.probation_ops_remaining = left.probation_ops_remaining;
 
+        // a one-sided split leaves the small side to be refilled by
+        // the writes that follow. if it were put on the usual
+        // probation it would split again after only a few writes,
+        // so an ascending workload would leave a trail of nodes no
+        // fuller than the probation period. instead, keep it from
+        // splitting until it has taken in twice what the full side
+        // kept, so each node left behind is fuller than the last,
+        // up to the limit of the probation counter.
+        match policy {
+            SplitPolicy::AppendOptimized => {
+                right.probation_ops_remaining =
+                    tf!((2 * split_point).min(u8::MAX as usize), u8);
+            }
+            SplitPolicy::PrependOptimized => {
+                left.probation_ops_remaining = tf!(
+                    (2 * (self.children() - split_point))
+                        .min(u8::MAX as usize),
+                    u8
+                );
+            }
+            _ => {}
+        }
+
         right.next = self.next;
 
         log::trace!(
@@ -2653,6 +2862,26 @@ mod test {
             .merge_overlay();
     }
 
+    #[test]
+    fn from_raw_checks_format_version() {
+        let mut inner = Inner::new_root(7);
+        let buf = inner.as_ref().to_vec();
+        let node = unsafe { Node::from_raw(&buf) }.unwrap();
+        assert_eq!(node.iter_index_pids().collect::<Vec<_>>(), vec![7]);
+
+        inner.header_mut().version = FORMAT_VERSION + 1;
+        let buf = inner.as_ref().to_vec();
+        assert!(matches!(
+            unsafe { Node::from_raw(&buf) },
+            Err(crate::Error::Unsupported(_))
+        ));
+
+        assert!(matches!(
+            unsafe { Node::from_raw(&buf[..3]) },
+            Err(crate::Error::Corruption { .. })
+        ));
+    }
+
     impl Arbitrary for Node {
         fn arbitrary<G: Gen>(g: &mut G) -> Node {
             Node {
@@ -2937,7 +3166,7 @@ mod test {
         };
 
         if node2.children() > 2 {
-            let (left, right) = node2.split();
+            let (left, right) = node2.split(SplitPolicy::Adaptive);
             let node3 = left.receive_merge(&right);
             assert_eq!(
                 node3.iter().collect::<Vec<_>>(),
@@ -3051,7 +3280,8 @@ mod test {
             ],
         );
 
-        Node { inner: Arc::new(node), overlay: Default::default() }.split();
+        Node { inner: Arc::new(node), overlay: Default::default() }
+            .split(SplitPolicy::Adaptive);
     }
 
     #[test]
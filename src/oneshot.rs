@@ -48,6 +48,13 @@ impl<T> OneShot<T> {
         (filler, future)
     }
 
+    /// Returns `true` if the `OneShot` has been filled,
+    /// or its `OneShotFiller` has been dropped, without
+    /// blocking.
+    pub fn is_filled(&self) -> bool {
+        self.mu.lock().filled
+    }
+
     /// Block on the `OneShot`'s completion
     /// or dropping of the `OneShotFiller`
     pub fn wait(self) -> Option<T> {
@@ -76,7 +83,7 @@ impl<T> OneShot<T> {
             let start = Instant::now();
             let res = self.cv.wait_for(&mut inner, timeout);
             if res.timed_out() {
-                return Err(std::sync::mpsc::RecvTimeoutError::Disconnected);
+                return Err(std::sync::mpsc::RecvTimeoutError::Timeout);
             }
             timeout = timeout.checked_sub(start.elapsed()).unwrap_or_default();
         }
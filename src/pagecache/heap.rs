@@ -246,6 +246,24 @@ impl Heap {
         self.slabs[slab_id as usize].free(slab_idx)
     }
 
+    /// The number of bytes that the item occupies on disk,
+    /// which is the size of its slab's slots.
+    pub fn object_size(&self, heap_id: HeapId) -> u64 {
+        heap_id.slab_size()
+    }
+
+    /// Extends the slab that holds items of `size` bytes by
+    /// enough slots to cover `bytes`, and makes them available
+    /// for future reservations. Returns the number of bytes
+    /// added to the slab.
+    pub fn preallocate(&self, size: u64, bytes: u64) -> Result<u64> {
+        assert!(size < 1 << 48);
+        let slab_id = size_to_slab_id(size);
+        let ret = self.slabs[slab_id as usize].preallocate(bytes);
+        log::trace!("Heap::preallocate({}, {}) -> {:?}", size, bytes, ret);
+        ret
+    }
+
     pub fn reserve(&self, size: u64, original_lsn: Lsn) -> Reservation {
         assert!(size < 1 << 48);
         let slab_id = size_to_slab_id(size);
@@ -367,6 +385,87 @@ impl Slab {
         }
     }
 
+    fn preallocate(&self, bytes: u64) -> Result<u64> {
+        let bs = slab_id_to_size(self.slab_id);
+        let slots = bytes.saturating_add(bs - 1) / bs;
+        let count = u32::try_from(slots).map_err(|_| {
+            Error::Unsupported(
+                "cannot preallocate more than 2^32 heap slots at once",
+            )
+        })?;
+        if count == 0 {
+            return Ok(0);
+        }
+
+        // claim the slots at the tip, so that nothing else
+        // writes to them while the file is being extended
+        let start = self.tip.fetch_add(count, Acquire);
+        let offset = u64::from(start) * bs;
+        let len = u64::from(count) * bs;
+
+        let res = self.extend(offset, len);
+
+        // even if extending failed, the claimed slots are still
+        // usable, because writing to a slot extends the file.
+        // push in reverse so that the lowest slots are reused first
+        for idx in (start..start + count).rev() {
+            self.free.push(idx, &pin());
+        }
+
+        res.map(|()| len)
+    }
+
+    fn extend(&self, offset: u64, len: u64) -> Result<()> {
+        if !self.allocate_blocks(offset, len)? {
+            // only write the last byte of the claimed slots, because
+            // set_len could truncate slots that were claimed after
+            // ours and already written
+            pwrite_all(&self.file, &[0], offset + len - 1)?;
+        }
+
+        self.file.sync_all()?;
+
+        Ok(())
+    }
+
+    // Allocates the blocks for a range up front instead of leaving
+    // a hole, so that later writes into it don't need to update
+    // the file's block map. Returns `false` if this is not
+    // supported.
+    #[allow(clippy::unnecessary_wraps)]
+    fn allocate_blocks(
+        &self,
+        #[allow(unused)] offset: u64,
+        #[allow(unused)] len: u64,
+    ) -> Result<bool> {
+        #[cfg(all(target_os = "linux", not(miri)))]
+        {
+            use std::os::unix::io::AsRawFd;
+
+            let ret = unsafe {
+                libc::fallocate(
+                    self.file.as_raw_fd(),
+                    0,
+                    offset.try_into().unwrap(),
+                    len.try_into().unwrap(),
+                )
+            };
+            if ret != 0 {
+                let err = std::io::Error::last_os_error();
+                if err.raw_os_error() == Some(libc::EOPNOTSUPP) {
+                    return Ok(false);
+                }
+                return Err(err.into());
+            }
+            Ok(true)
+        }
+
+        #[cfg(not(all(target_os = "linux", not(miri))))]
+        {
+            Ok(false)
+        }
+    }
+
     fn free(&self, idx: u32) {
         self.punch_hole(idx);
         self.free.push(idx, &pin());
@@ -4,7 +4,10 @@ use std::{
     sync::atomic::AtomicPtr,
 };
 
-use crate::{pagecache::*, *};
+use crate::{
+    pagecache::{Snapshot, *},
+    *,
+};
 
 macro_rules! io_fail {
     ($self:expr, $e:expr) => {
@@ -303,6 +306,16 @@ pub(crate) struct IoBufs {
     pub segment_accountant: Mutex<SegmentAccountant>,
     pub segment_cleaner: SegmentCleaner,
     deferred_segment_ops: stack::Stack<SegmentOp>,
+
+    // Sealed buffers that could not be written because of a
+    // transient error, kept so that they can be written again
+    // by `clear_transient_error` instead of leaving a hole in
+    // the log.
+    failed_writes: Mutex<Vec<Arc<IoBuf>>>,
+
+    // Bytes written to the log and the heap since startup,
+    // used to calculate write amplification.
+    pub written_bytes: AtomicU64,
 }
 
 impl Drop for IoBufs {
@@ -406,6 +419,8 @@ impl IoBufs {
             segment_accountant: Mutex::new(segment_accountant),
             segment_cleaner,
             deferred_segment_ops: stack::Stack::default(),
+            failed_writes: Mutex::new(vec![]),
+            written_bytes: AtomicU64::new(0),
         })
     }
 
@@ -600,6 +615,7 @@ impl IoBufs {
 
             // write the blob file
             heap_reservation.complete(&heap_buf)?;
+            self.written_bytes.fetch_add(heap_buf.len() as u64, Relaxed);
         } else {
             #[cfg(feature = "metrics")]
             let _ = Measure::new(&M.serialize);
@@ -737,44 +753,13 @@ impl IoBufs {
         let stored_max_stable_lsn = iobuf.stored_max_stable_lsn;
 
         io_fail!(self, "buffer write");
-        let f = &self.config.file;
-        pwrite_all(f, data, log_offset)?;
-        if !self.config.temporary {
-            if iobuf.from_tip {
-                f.sync_all()?;
-            } else if cfg!(not(target_os = "linux")) {
-                f.sync_data()?;
-            } else {
-                #[allow(clippy::assertions_on_constants)]
-                {
-                    assert!(cfg!(target_os = "linux"));
-                }
-
-                #[cfg(target_os = "linux")]
-                {
-                    use std::os::unix::io::AsRawFd;
-                    let ret = unsafe {
-                        libc::sync_file_range(
-                            f.as_raw_fd(),
-                            i64::try_from(log_offset).unwrap(),
-                            i64::try_from(total_len).unwrap(),
-                            libc::SYNC_FILE_RANGE_WAIT_BEFORE
-                                | libc::SYNC_FILE_RANGE_WRITE
-                                | libc::SYNC_FILE_RANGE_WAIT_AFTER,
-                        )
-                    };
-                    if ret < 0 {
-                        let err = std::io::Error::last_os_error();
-                        if let Some(libc::ENOSYS) = err.raw_os_error() {
-                            f.sync_all()?;
-                        } else {
-                            return Err(err.into());
-                        }
-                    }
-                }
-            }
+        if let Err(e) = self.write_and_sync(data, log_offset, iobuf.from_tip) {
+            self.stash_failed_write(&iobuf, e);
+            return Err(e);
         }
 
+        self.written_bytes.fetch_add(total_len as u64, Relaxed);
+
         // get rid of the iobuf as quickly as possible because
         // it is a huge allocation
         drop(iobuf);
@@ -824,6 +809,102 @@ impl IoBufs {
         self.sa_stabilize(current_max_header_stable_lsn)
     }
 
+    // Writes a sealed buffer's bytes to the log file at
+    // `log_offset` and syncs them. Rewriting the same bytes
+    // is harmless, so this may be retried after it fails.
+    fn write_and_sync(
+        &self,
+        data: &[u8],
+        log_offset: LogOffset,
+        from_tip: bool,
+    ) -> Result<()> {
+        #[cfg(feature = "failpoints")]
+        {
+            if crate::fail::is_active("buffer write transient") {
+                return Err(Error::Io(
+                    std::io::ErrorKind::Interrupted,
+                    "failpoint: transient buffer write error",
+                ));
+            }
+        }
+
+        let f = &self.config.file;
+        pwrite_all(f, data, log_offset)?;
+        if !self.config.temporary {
+            if from_tip {
+                f.sync_all()?;
+            } else if cfg!(not(target_os = "linux")) {
+                f.sync_data()?;
+            } else {
+                #[allow(clippy::assertions_on_constants)]
+                {
+                    assert!(cfg!(target_os = "linux"));
+                }
+
+                #[cfg(target_os = "linux")]
+                {
+                    use std::os::unix::io::AsRawFd;
+                    let ret = unsafe {
+                        libc::sync_file_range(
+                            f.as_raw_fd(),
+                            i64::try_from(log_offset).unwrap(),
+                            i64::try_from(data.len()).unwrap(),
+                            libc::SYNC_FILE_RANGE_WAIT_BEFORE
+                                | libc::SYNC_FILE_RANGE_WRITE
+                                | libc::SYNC_FILE_RANGE_WAIT_AFTER,
+                        )
+                    };
+                    if ret < 0 {
+                        let err = std::io::Error::last_os_error();
+                        if let Some(libc::ENOSYS) = err.raw_os_error() {
+                            f.sync_all()?;
+                        } else {
+                            return Err(err.into());
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Keeps a sealed buffer whose write failed with a
+    // transient error, see `failed_writes`.
+    pub(crate) fn stash_failed_write(&self, iobuf: &Arc<IoBuf>, error: Error) {
+        if error.is_transient() {
+            self.failed_writes.lock().push(iobuf.clone());
+        }
+    }
+
+    /// Writes out the buffers that failed because of a
+    /// transient error and, if that succeeds, clears the
+    /// global error so that the log accepts writes again.
+    pub(crate) fn clear_transient_error(&self) -> Result<()> {
+        match self.config.global_error() {
+            Ok(()) => return Ok(()),
+            Err(e) if !e.is_transient() => return Err(e),
+            Err(_) => {}
+        }
+
+        let mut failed = std::mem::take(&mut *self.failed_writes.lock());
+        failed.sort_unstable_by_key(|iobuf| iobuf.lsn);
+
+        let mut failed = failed.into_iter();
+        while let Some(iobuf) = failed.next() {
+            if let Err(e) = self.write_to_log(iobuf) {
+                // the buffer that failed was stashed again
+                // by `write_to_log`
+                self.failed_writes.lock().extend(failed);
+                return Err(e);
+            }
+        }
+
+        self.config.reset_global_error();
+
+        Ok(())
+    }
+
     // It's possible that IO buffers are written out of order!
     // So we need to use this to keep track of them, and only
     // increment self.stable. If we didn't do this, then we would
@@ -1217,7 +1298,10 @@ pub(in crate::pagecache) fn maybe_seal_and_write_iobuf(
 
     // if writers is 0, it's our responsibility to write the buffer.
     if header::n_writers(sealed) == 0 {
-        iobufs.config.global_error()?;
+        if let Err(e) = iobufs.config.global_error() {
+            iobufs.stash_failed_write(iobuf, e);
+            return Err(e);
+        }
         trace!(
             "asynchronously writing iobuf with lsn {} to log from maybe_seal",
             lsn
@@ -88,6 +88,12 @@ impl Log {
         self.iobufs.stable()
     }
 
+    /// returns the highest log sequence number reserved so
+    /// far, which covers every write that has completed
+    pub fn max_reserved_lsn(&self) -> Lsn {
+        self.iobufs.max_reserved_lsn.load(Acquire)
+    }
+
     /// blocks until the specified log sequence number has
     /// been made stable on disk. Returns the number of
     /// bytes written during this call. this is appropriate
@@ -455,6 +461,8 @@ impl Log {
         // to 0 and it's sealed then we should write it to storage.
         if header::n_writers(header) == 0 && header::is_sealed(header) {
             if let Err(e) = self.config.global_error() {
+                self.iobufs.stash_failed_write(iobuf, e);
+
                 let intervals = self.iobufs.intervals.lock();
 
                 // having held the mutex makes this linearized
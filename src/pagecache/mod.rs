@@ -358,6 +358,60 @@ impl<'a> RecoveryGuard<'a> {
     }
 }
 
+/// A snapshot of the page cache counters, returned by
+/// `Db::cache_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Page reads served from memory.
+    pub hits: u64,
+    /// Page reads that had to be pulled from disk.
+    pub misses: u64,
+    /// Pages paged out of memory to stay within
+    /// `cache_capacity`.
+    pub evictions: u64,
+    /// An estimate of the bytes of pages currently tracked
+    /// by the cache, with each page rounded up to the next
+    /// power of two.
+    pub resident_bytes: u64,
+}
+
+/// A summary of the tree nodes known to the page cache,
+/// returned by `Db::node_stats`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct NodeStats {
+    /// Nodes currently held in memory.
+    pub resident: u64,
+    /// Nodes that have been paged out, or that have not been
+    /// read since the database was opened.
+    pub paged_out: u64,
+    /// The total in-memory size of the resident nodes.
+    pub resident_bytes: u64,
+    /// Resident nodes bucketed by in-memory size. Bucket `i`
+    /// counts the nodes whose size is greater than `2^(i-1)`
+    /// and at most `2^i` bytes.
+    pub size_histogram: Vec<u64>,
+}
+
+impl NodeStats {
+    /// The average in-memory size of the resident nodes.
+    pub fn average_resident_size(&self) -> u64 {
+        self.resident_bytes.checked_div(self.resident).unwrap_or(0)
+    }
+}
+
+/// How far recovery has read through the log while opening
+/// a database, passed to the `Config::recovery_progress`
+/// callback and returned by `Db::recovery_stats`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RecoveryProgress {
+    /// Log entries replayed on top of the last snapshot.
+    pub log_entries: u64,
+    /// Bytes of the log scanned past the last snapshot.
+    pub bytes_scanned: u64,
+    /// Time spent recovering so far.
+    pub elapsed: std::time::Duration,
+}
+
 /// A page consists of a sequence of state transformations
 /// with associated storage parameters like disk pos, lsn, time.
 #[derive(Debug, Clone)]
@@ -439,6 +493,7 @@ impl Deref for PageCache {
 
 pub struct PageCacheInner {
     was_recovered: bool,
+    recovery_stats: RecoveryProgress,
     pub(crate) config: RunningConfig,
     inner: PageTable,
     next_pid_to_allocate: Mutex<PageId>,
@@ -448,6 +503,11 @@ pub struct PageCacheInner {
     #[doc(hidden)]
     pub log: Log,
     lru: Lru,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    evictions: AtomicU64,
+    // key and value bytes written by tree operations
+    logical_bytes_written: AtomicU64,
 
     idgen: AtomicU64,
     idgen_persists: AtomicU64,
@@ -512,7 +572,7 @@ impl PageCache {
         // try to pull any existing snapshot off disk, and
         // apply any new data to it to "catch-up" the
         // snapshot before loading it.
-        let snapshot = read_snapshot_or_default(&config)?;
+        let (snapshot, recovery_stats) = read_snapshot_or_default(&config)?;
 
         config.heap.gc_unknown_items(&snapshot);
 
@@ -527,7 +587,7 @@ impl PageCache {
 
             let paused_faults = crate::fail::pause_faults();
 
-            let snapshot2 = read_snapshot_or_default(&config)
+            let (snapshot2, _) = read_snapshot_or_default(&config)
                 .expect("second read snapshot");
 
             crate::fail::restore_faults(paused_faults);
@@ -583,6 +643,7 @@ impl PageCache {
 
         let mut pc = PageCacheInner {
             was_recovered: false,
+            recovery_stats,
             config: config.clone(),
             free: Arc::new(Mutex::new(FastSet8::default())),
             idgen: AtomicU64::new(0),
@@ -591,6 +652,10 @@ impl PageCache {
             inner: PageTable::default(),
             log: Log::start(config, &snapshot)?,
             lru,
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            logical_bytes_written: AtomicU64::new(0),
             next_pid_to_allocate: Mutex::new(0),
             snapshot_min_lsn: AtomicLsn::new(snapshot.stable_lsn.unwrap_or(0)),
             links: AtomicU64::new(0),
@@ -1073,6 +1138,22 @@ impl PageCacheInner {
         ret
     }
 
+    /// Calls `attempt_gc` for everything that is currently
+    /// queued for cleaning, returning the number of pages
+    /// that were moved. Segments that become eligible for
+    /// cleaning during this call are left for later.
+    #[cfg(not(miri))]
+    pub(crate) fn gc_all(&self) -> Result<usize> {
+        let pending = self.log.iobufs.segment_cleaner.pending_pops();
+        let mut moved = 0;
+        for _ in 0..pending {
+            if self.attempt_gc()? {
+                moved += 1;
+            }
+        }
+        Ok(moved)
+    }
+
     /// Initiate an atomic sequence of writes to the
     /// underlying log. Returns a `RecoveryGuard` which,
     /// when dropped, will record the current max reserved
@@ -1407,13 +1488,48 @@ impl PageCacheInner {
         Ok(())
     }
 
+    /// Returns how much of the log recovery replayed while
+    /// this `PageCache` was being started.
+    pub(crate) const fn recovery_stats(&self) -> RecoveryProgress {
+        self.recovery_stats
+    }
+
+    /// Returns the current values of the cache counters.
+    pub(crate) fn cache_stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.cache_hits.load(Relaxed),
+            misses: self.cache_misses.load(Relaxed),
+            evictions: self.evictions.load(Relaxed),
+            resident_bytes: self.lru.resident_bytes(),
+        }
+    }
+
+    /// Records the key and value bytes of a write made by a
+    /// tree operation, for `write_amplification`.
+    pub(crate) fn record_logical_write(&self, bytes: usize) {
+        self.logical_bytes_written.fetch_add(bytes as u64, Relaxed);
+    }
+
+    /// Divides the bytes written to the log and the heap since
+    /// startup by the key and value bytes written by tree
+    /// operations, or returns 0.0 if nothing has been written.
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::float_arithmetic)]
+    pub(crate) fn write_amplification(&self) -> f64 {
+        let logical = self.logical_bytes_written.load(Relaxed);
+        if logical == 0 {
+            return 0.0;
+        }
+        let physical = self.log.iobufs.written_bytes.load(Relaxed);
+        physical as f64 / logical as f64
+    }
+
     /// Traverses all files and calculates their total physical
     /// size, then traverses all pages and calculates their
     /// total logical size, then divides the physical size
     /// by the logical size.
     #[allow(clippy::cast_precision_loss)]
     #[allow(clippy::float_arithmetic)]
-    #[doc(hidden)]
     pub(crate) fn space_amplification(&self) -> Result<f64> {
         let on_disk_bytes = self.size_on_disk()? as f64;
         let logical_size = (self.logical_size_of_all_tree_pages()?
@@ -1458,6 +1574,137 @@ impl PageCacheInner {
         Ok(size)
     }
 
+    /// Walks the page table without paging anything in, and
+    /// summarizes the resident and paged-out tree nodes.
+    pub(crate) fn node_stats(&self) -> NodeStats {
+        let guard = pin();
+        let min_pid = COUNTER_PID + 1;
+        let next_pid_to_allocate = *self.next_pid_to_allocate.lock();
+
+        let mut ret = NodeStats::default();
+        for pid in min_pid..next_pid_to_allocate {
+            if !self.inner.contains_pid(pid, &guard) {
+                continue;
+            }
+
+            let page_view = self.inner.get(pid, &guard);
+            if page_view.is_free() {
+                continue;
+            }
+
+            if let Some(rss) = page_view.rss() {
+                let bucket = usize::try_from(
+                    rss.next_power_of_two().trailing_zeros(),
+                )
+                .unwrap();
+                if ret.size_histogram.len() <= bucket {
+                    ret.size_histogram.resize(bucket + 1, 0);
+                }
+                ret.size_histogram[bucket] += 1;
+                ret.resident += 1;
+                ret.resident_bytes += rss;
+            } else {
+                ret.paged_out += 1;
+            }
+        }
+        ret
+    }
+
+    /// Returns the number of bytes that the base and every
+    /// delta of the page at `pid` occupy on disk. Fragments
+    /// stored in the heap are sized from their slot without
+    /// any IO, while fragments stored in the log have their
+    /// message read back to learn its length.
+    pub(crate) fn stored_size(
+        &self,
+        pid: PageId,
+        guard: &Guard,
+    ) -> Result<u64> {
+        loop {
+            let page_view = self.inner.get(pid, guard);
+
+            let size_res: Result<u64> = page_view
+                .cache_infos
+                .iter()
+                .map(|ci| match ci.pointer {
+                    DiskPtr::Heap(_, heap_id) => {
+                        Ok(self.config.heap.object_size(heap_id))
+                    }
+                    DiskPtr::Inline(_) => {
+                        match self.log.read(pid, ci.lsn, ci.pointer)? {
+                            LogRead::Inline(_, _, len) => Ok(u64::from(len)),
+                            other => {
+                                debug!(
+                                    "read unexpected page for size: {:?}",
+                                    other
+                                );
+                                Err(Error::corruption(Some(ci.pointer)))
+                            }
+                        }
+                    }
+                })
+                .sum();
+
+            match size_res {
+                Ok(size) => return Ok(size),
+                // the page may have been rewritten and its old
+                // fragments reclaimed while they were being read
+                Err(e)
+                    if self.inner.get(pid, guard).cache_infos
+                        == page_view.cache_infos =>
+                {
+                    return Err(e)
+                }
+                Err(_) => continue,
+            }
+        }
+    }
+
+    /// Returns the ids of the resident tree nodes, roughly
+    /// from least to most recently accessed. See
+    /// `Lru::coldest` for how approximate the order is.
+    pub(crate) fn coldest_pids(&self) -> Vec<PageId> {
+        self.lru
+            .coldest()
+            .into_iter()
+            .filter(|pid| *pid > COUNTER_PID && *pid != BATCH_MANIFEST_PID)
+            .collect()
+    }
+
+    /// Returns the node stored at `pid` only if it is
+    /// currently resident, without recording the access in
+    /// the cache and without reading anything from disk.
+    pub(crate) fn get_resident<'g>(
+        &self,
+        pid: PageId,
+        guard: &'g Guard,
+    ) -> Option<NodeView<'g>> {
+        if !self.inner.contains_pid(pid, guard) {
+            return None;
+        }
+
+        let page_view = self.inner.get(pid, guard);
+        page_view.rss()?;
+        Some(NodeView(page_view))
+    }
+
+    /// Pages out every resident tree node, so that each one is
+    /// read back from disk the next time it is used. Returns
+    /// the number of nodes that were paged out.
+    pub(crate) fn page_out_all(&self) -> Result<usize> {
+        let guard = pin();
+        let min_pid = COUNTER_PID + 1;
+        let next_pid_to_allocate = *self.next_pid_to_allocate.lock();
+
+        let to_evict: Vec<PageId> = (min_pid..next_pid_to_allocate)
+            .filter(|pid| self.get_resident(*pid, &guard).is_some())
+            .collect();
+        let paged_out = to_evict.len();
+
+        self.page_out(to_evict, &guard)?;
+        Ok(paged_out)
+    }
+
     fn logical_size_of_all_tree_pages(&self) -> Result<u64> {
         let guard = pin();
         let min_pid = COUNTER_PID + 1;
@@ -1642,6 +1889,29 @@ impl PageCacheInner {
         &self,
         pid: PageId,
         guard: &'g Guard,
+    ) -> Result<Option<NodeView<'g>>> {
+        self.get_inner(pid, false, guard)
+    }
+
+    /// Try to retrieve a page by its logical ID without
+    /// recording the access in the cache or causing any
+    /// other page to be evicted. If the page is not
+    /// resident, it is read from disk into a copy that
+    /// lives only as long as the guard, and the cache
+    /// is left untouched.
+    pub(crate) fn get_cold<'g>(
+        &self,
+        pid: PageId,
+        guard: &'g Guard,
+    ) -> Result<Option<NodeView<'g>>> {
+        self.get_inner(pid, true, guard)
+    }
+
+    fn get_inner<'g>(
+        &self,
+        pid: PageId,
+        cold: bool,
+        guard: &'g Guard,
     ) -> Result<Option<NodeView<'g>>> {
         trace!("getting page iterator for pid {}", pid);
         #[cfg(feature = "metrics")]
@@ -1669,8 +1939,10 @@ impl PageCacheInner {
             }
 
             if page_view.update.is_some() {
+                self.cache_hits.fetch_add(1, Relaxed);
+
                 // possibly evict an item now that our cache has grown
-                if let Some(rss) = page_view.rss() {
+                if let Some(rss) = page_view.rss().filter(|_| !cold) {
                     self.lru_access(pid, rss, guard)?;
                 }
                 return Ok(Some(NodeView(page_view)));
@@ -1708,6 +1980,8 @@ impl PageCacheInner {
             };
         };
 
+        self.cache_misses.fetch_add(1, Relaxed);
+
         let (base_slice, links) = updates.split_at_mut(1);
 
         let base: &mut Node = base_slice[0].as_node_mut();
@@ -1720,11 +1994,30 @@ impl PageCacheInner {
         updates.truncate(1);
         let base_owned = updates.pop().unwrap();
 
+        // a node with a merging child must be installed so that
+        // the traversal can help the merge along, but anything
+        // else can be handed out without touching the cache.
+        let detach = cold && base_owned.as_node().merging_child.is_none();
+
         let page = Owned::new(Page {
             update: Some(base_owned),
             cache_infos: page_view.cache_infos.clone(),
         });
 
+        if detach {
+            trace!("reading pid {} without installing it", pid);
+            let detached = page.into_shared(guard);
+
+            unsafe {
+                guard.defer_destroy(detached);
+            }
+
+            let mut page_view2 = page_view;
+            page_view2.read = detached;
+
+            return Ok(Some(NodeView(page_view2)));
+        }
+
         debug_delay();
         let result = page_view.entry.compare_and_set(
             page_view.read,
@@ -1752,7 +2045,7 @@ impl PageCacheInner {
         } else {
             trace!("fix-up for pid {} failed", pid);
 
-            self.get(pid, guard)
+            self.get_inner(pid, cold, guard)
         }
     }
 
@@ -1898,7 +2191,11 @@ impl PageCacheInner {
         }
     }
 
-    fn page_out(&self, to_evict: Vec<PageId>, guard: &Guard) -> Result<()> {
+    pub(crate) fn page_out(
+        &self,
+        to_evict: Vec<PageId>,
+        guard: &Guard,
+    ) -> Result<()> {
         #[cfg(feature = "metrics")]
         let _measure = Measure::new(&M.page_out);
         for pid in to_evict {
@@ -1937,6 +2234,13 @@ impl PageCacheInner {
                         guard.defer_destroy(page_view.read);
                     }
 
+                    self.evictions.fetch_add(1, Relaxed);
+
+                    if let Some(on_evict) = &self.config.on_evict {
+                        let size = page_view.rss().unwrap_or(0);
+                        on_evict.call(pid, usize::try_from(size).unwrap());
+                    }
+
                     break;
                 }
                 // keep looping until we page this sucka out
@@ -1958,36 +2262,25 @@ impl PageCacheInner {
         iobuf::make_durable(&self.log.iobufs, lsn)?;
 
         let (header, bytes) = match self.log.read(pid, lsn, pointer) {
-            Ok(LogRead::Inline(header, buf, _len)) => {
-                assert_eq!(
-                    header.pid, pid,
-                    "expected pid {} on pull of pointer {}, \
-                     but got {} instead",
-                    pid, pointer, header.pid
-                );
-                assert_eq!(
-                    header.segment_number, expected_segment_number,
-                    "expected segment number {:?} on pull of pointer {}, \
-                     but got segment number {:?} instead",
-                    expected_segment_number, pointer, header.segment_number
-                );
-                Ok((header, buf))
-            }
-            Ok(LogRead::Heap(header, buf, _heap_id, _inline_len)) => {
-                assert_eq!(
-                    header.pid, pid,
-                    "expected pid {} on pull of pointer {}, \
-                     but got {} instead",
-                    pid, pointer, header.pid
-                );
-                assert_eq!(
-                    header.segment_number, expected_segment_number,
-                    "expected segment number {:?} on pull of pointer {}, \
-                     but got segment number {:?} instead",
-                    expected_segment_number, pointer, header.segment_number
-                );
-
-                Ok((header, buf))
+            Ok(LogRead::Inline(header, buf, _))
+            | Ok(LogRead::Heap(header, buf, _, _)) => {
+                if header.pid != pid {
+                    error!(
+                        "expected pid {} on pull of pointer {}, \
+                         but got {} instead",
+                        pid, pointer, header.pid
+                    );
+                    Err(Error::corruption(Some(pointer)))
+                } else if header.segment_number != expected_segment_number {
+                    error!(
+                        "expected segment number {:?} on pull of pointer {}, \
+                         but got segment number {:?} instead",
+                        expected_segment_number, pointer, header.segment_number
+                    );
+                    Err(Error::corruption(Some(pointer)))
+                } else {
+                    Ok((header, buf))
+                }
             }
             Ok(other) => {
                 debug!("read unexpected page: {:?}", other);
@@ -2002,7 +2295,17 @@ impl PageCacheInner {
         // We create this &mut &[u8] to assist the `Serializer`
         // implementation that incrementally consumes bytes
         // without taking ownership of them.
-        let buf = &mut bytes.as_slice();
+        let mut buf = bytes.as_slice();
+
+        #[cfg(feature = "failpoints")]
+        {
+            if crate::fail::is_active("pull torn message") {
+                // as if the message was torn after its crc passed
+                buf = &buf[..buf.len() / 2];
+            }
+        }
+
+        let buf = &mut buf;
 
         let update_res = {
             #[cfg(feature = "metrics")]
@@ -2021,12 +2324,27 @@ impl PageCacheInner {
                 }
                 Free => Ok(Update::Free),
                 Corrupted | Canceled | Cap | BatchManifest => {
-                    panic!("unexpected pull: {:?}", header.kind)
+                    error!(
+                        "unexpected pull of {:?} for pid {} at pointer {}",
+                        header.kind, pid, pointer
+                    );
+                    Err(Error::corruption(Some(pointer)))
                 }
             }
         };
 
-        let update = update_res.expect("failed to deserialize data");
+        // a message that passed its crc check but fails to
+        // deserialize fails this read instead of the process
+        let update = match update_res {
+            Ok(update) => update,
+            Err(e) => {
+                error!(
+                    "failed to deserialize pid {} at pointer {}: {:?}",
+                    pid, pointer, e
+                );
+                return Err(Error::corruption(Some(pointer)));
+            }
+        };
 
         // TODO this feels racy, test it better?
         if let Update::Free = update {
@@ -58,7 +58,7 @@
 
 use std::{collections::BTreeSet, mem};
 
-use super::PageState;
+use super::{PageState, Snapshot};
 
 use crate::pagecache::*;
 use crate::*;
@@ -118,6 +118,14 @@ impl SegmentCleaner {
         None
     }
 
+    // counts both the queued pids and the segments holding
+    // them, which bounds the number of `pop` calls it takes
+    // to drain what is queued right now.
+    pub(crate) fn pending_pops(&self) -> usize {
+        let inner = self.inner.lock();
+        inner.len() + inner.values().map(BTreeSet::len).sum::<usize>()
+    }
+
     fn add_pids(&self, offset: LogOffset, pids: BTreeSet<PageId>) {
         let mut inner = self.inner.lock();
         let prev = inner.insert(offset, pids);
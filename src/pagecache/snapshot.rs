@@ -1,10 +1,17 @@
+use std::time::Instant;
+
 use crate::*;
 
 use super::{
     arr_to_u32, pwrite_all, raw_segment_iter_from, u32_to_arr, u64_to_arr,
     BasedBuf, DiskPtr, HeapId, LogIter, LogKind, LogOffset, Lsn, MessageKind,
+    RecoveryProgress,
 };
 
+// how many replayed log entries pass between calls to
+// the `Config::recovery_progress` callback
+const RECOVERY_PROGRESS_INTERVAL: u64 = 64 * 1024;
+
 /// A snapshot of the state required to quickly restart
 /// the `PageCache` and `SegmentAccountant`.
 #[derive(PartialEq, Debug, Default)]
@@ -253,7 +260,8 @@ fn advance_snapshot(
     mut iter: LogIter,
     mut snapshot: Snapshot,
     config: &RunningConfig,
-) -> Result<Snapshot> {
+    start: Instant,
+) -> Result<(Snapshot, RecoveryProgress)> {
     #[cfg(feature = "metrics")]
     let _measure = Measure::new(&M.advance_snapshot);
 
@@ -261,6 +269,9 @@ fn advance_snapshot(
 
     let old_stable_lsn = snapshot.stable_lsn;
 
+    let scan_start = old_stable_lsn.unwrap_or(0);
+    let mut progress = RecoveryProgress::default();
+
     for (log_kind, pid, lsn, ptr) in &mut iter {
         trace!(
             "in advance_snapshot looking at item with pid {} lsn {} ptr {}",
@@ -282,6 +293,16 @@ fn advance_snapshot(
         }
 
         snapshot.apply(log_kind, pid, lsn, ptr)?;
+
+        progress.log_entries += 1;
+        progress.bytes_scanned =
+            u64::try_from(lsn - scan_start).unwrap_or(progress.bytes_scanned);
+        if progress.log_entries % RECOVERY_PROGRESS_INTERVAL == 0 {
+            if let Some(on_progress) = &config.recovery_progress {
+                progress.elapsed = start.elapsed();
+                on_progress.call(progress);
+            }
+        }
     }
 
     // `snapshot.tip_lid` can be set based on 4 possibilities for the tip of the
@@ -466,12 +487,25 @@ fn advance_snapshot(
     #[cfg(feature = "event_log")]
     config.event_log.recovered_lsn(snapshot.stable_lsn.unwrap_or(0));
 
-    Ok(snapshot)
+    if let Some(cur_lsn) = iter.cur_lsn {
+        progress.bytes_scanned = u64::try_from(cur_lsn - scan_start)
+            .unwrap_or(progress.bytes_scanned);
+    }
+    progress.elapsed = start.elapsed();
+    if let Some(on_progress) = &config.recovery_progress {
+        on_progress.call(progress);
+    }
+
+    Ok((snapshot, progress))
 }
 
 /// Read a `Snapshot` or generate a default, then advance it to
 /// the tip of the data file, if present.
-pub fn read_snapshot_or_default(config: &RunningConfig) -> Result<Snapshot> {
+pub fn read_snapshot_or_default(
+    config: &RunningConfig,
+) -> Result<(Snapshot, RecoveryProgress)> {
+    let start = Instant::now();
+
     // NB we want to error out if the read snapshot was corrupted.
     // We only use a default Snapshot when there is no snapshot found.
     let last_snap = read_snapshot(config)?.unwrap_or_default();
@@ -479,9 +513,7 @@ pub fn read_snapshot_or_default(config: &RunningConfig) -> Result<Snapshot> {
     let log_iter =
         raw_segment_iter_from(last_snap.stable_lsn.unwrap_or(0), config)?;
 
-    let res = advance_snapshot(log_iter, last_snap, config)?;
-
-    Ok(res)
+    advance_snapshot(log_iter, last_snap, config, start)
 }
 
 /// Read a `Snapshot` from disk.
@@ -52,8 +52,41 @@ impl Error {
     pub(crate) const fn corruption(at: Option<DiskPtr>) -> Error {
         Error::Corruption { at }
     }
+
+    /// Returns `true` for errors caused by the environment
+    /// rather than by the state of the database: a full disk,
+    /// or an I/O call that was interrupted or would have
+    /// blocked. Every other error, including other I/O errors
+    /// such as a failed `fsync`, may mean that a write was lost
+    /// and is not transient. Once the cause has been fixed,
+    /// `Db::clear_transient_error` can be used to resume
+    /// writing after a transient error.
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::Io(kind, reason) => {
+                matches!(
+                    kind,
+                    io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                ) || reason == NO_SPACE_LEFT
+            }
+            _ => false,
+        }
+    }
 }
 
+// `Error::Io` only keeps the kind of an `io::Error`, and there
+// is no stable kind for a full disk, so it is recognized by the
+// reason set when converting from one of `DISK_FULL_CODES`.
+const NO_SPACE_LEFT: &str = "no space left on device";
+
+// The raw OS error codes that mean the disk is full.
+#[cfg(not(windows))]
+const DISK_FULL_CODES: &[i32] = &[libc::ENOSPC];
+
+// ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL
+#[cfg(windows)]
+const DISK_FULL_CODES: &[i32] = &[39, 112];
+
 impl Eq for Error {}
 
 impl PartialEq for Error {
@@ -95,7 +128,12 @@ impl PartialEq for Error {
 impl From<io::Error> for Error {
     #[inline]
     fn from(io_error: io::Error) -> Self {
-        Error::Io(io_error.kind(), "io error")
+        match io_error.raw_os_error() {
+            Some(code) if DISK_FULL_CODES.contains(&code) => {
+                Error::Io(io_error.kind(), NO_SPACE_LEFT)
+            }
+            _ => Error::Io(io_error.kind(), "io error"),
+        }
     }
 }
 
@@ -161,3 +199,23 @@ impl Display for Error {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_disks_and_interrupts_are_transient() {
+        for code in DISK_FULL_CODES {
+            let full = io::Error::from_raw_os_error(*code);
+            assert!(Error::from(full).is_transient());
+        }
+
+        let interrupted = io::Error::from(io::ErrorKind::Interrupted);
+        assert!(Error::from(interrupted).is_transient());
+
+        let denied = io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(!Error::from(denied).is_transient());
+        assert!(!Error::ReportableBug("bug").is_transient());
+    }
+}
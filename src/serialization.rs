@@ -489,9 +489,12 @@ impl Serialize for Node {
             return Err(Error::corruption(None));
         }
         let len = usize::try_from(u64::deserialize(buf)?).unwrap();
+        if buf.len() < len {
+            return Err(Error::corruption(None));
+        }
 
         #[allow(unsafe_code)]
-        let sst = unsafe { Node::from_raw(&buf[..len]) };
+        let sst = unsafe { Node::from_raw(&buf[..len])? };
 
         *buf = &buf[len..];
         Ok(sst)
@@ -0,0 +1,113 @@
+use std::ops::{Bound, RangeBounds};
+
+use super::*;
+
+/// A read-only, point-in-time view of a `Tree`, created
+/// with [`Tree::snapshot`]. Reads through a `Snapshot`
+/// see the data exactly as it was when the snapshot was
+/// taken, regardless of any writes that happen afterwards.
+///
+/// A `Snapshot` shares the immutable leaf nodes of the tree
+/// rather than copying them, so creating one is cheap in
+/// memory. But every leaf that is written to after the
+/// snapshot is taken gets a new version while the snapshot
+/// keeps the old one alive, and leaves that were paged out
+/// are pulled into memory while the snapshot is created.
+/// Under heavy writes, holding a snapshot open can therefore
+/// cost up to the full in-memory size of the tree on top of
+/// the normal cache, until it is dropped. The cache capacity
+/// does not account for this memory.
+///
+/// Cloning a `Snapshot` is cheap and the clones share the
+/// same nodes.
+#[derive(Clone)]
+pub struct Snapshot {
+    // sorted by low key, covering the whole keyspace
+    leaves: Arc<[Node]>,
+}
+
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot").field("leaves", &self.leaves.len()).finish()
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn from_leaves(leaves: Vec<Node>) -> Snapshot {
+        Snapshot { leaves: leaves.into() }
+    }
+
+    fn leaf_index(&self, key: &[u8]) -> usize {
+        self.leaves.partition_point(|leaf| leaf.lo() <= key).saturating_sub(1)
+    }
+
+    /// Retrieve a value from the `Snapshot` if it existed
+    /// when the snapshot was taken.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<IVec> {
+        let key = key.as_ref();
+        let leaf = &self.leaves[self.leaf_index(key)];
+        leaf.node_kv_pair(key).1.map(IVec::from)
+    }
+
+    /// Returns `true` if the `Snapshot` contains a value for
+    /// the specified key.
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Iterate over all key-value pairs in the `Snapshot`,
+    /// in ascending key order.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = (IVec, IVec)> {
+        self.range::<&[u8], _>(..)
+    }
+
+    /// Iterate over the key-value pairs in the `Snapshot`
+    /// that fall within `range`, in ascending key order.
+    pub fn range<K, R>(
+        &self,
+        range: R,
+    ) -> impl '_ + Iterator<Item = (IVec, IVec)>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let to_ivec = |bound: Bound<&K>| match bound {
+            Bound::Included(k) => Bound::Included(IVec::from(k.as_ref())),
+            Bound::Excluded(k) => Bound::Excluded(IVec::from(k.as_ref())),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        let mut lo = to_ivec(range.start_bound());
+        let hi = to_ivec(range.end_bound());
+        let mut idx = match lo {
+            Bound::Included(ref start) | Bound::Excluded(ref start) => {
+                self.leaf_index(start)
+            }
+            Bound::Unbounded => 0,
+        };
+
+        std::iter::from_fn(move || {
+            while let Some(leaf) = self.leaves.get(idx) {
+                if !leaf.contains_lower_bound(&lo, true) {
+                    lo = Bound::Included(leaf.lo().into());
+                }
+
+                if let Some((key, value)) = leaf.successor(&lo) {
+                    let in_range = match hi {
+                        Bound::Included(ref end) => key <= *end,
+                        Bound::Excluded(ref end) => key < *end,
+                        Bound::Unbounded => true,
+                    };
+                    if !in_range {
+                        idx = self.leaves.len();
+                        return None;
+                    }
+                    lo = Bound::Excluded(key.clone());
+                    return Some((key, value));
+                }
+
+                idx += 1;
+            }
+            None
+        })
+    }
+}
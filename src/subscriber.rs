@@ -3,7 +3,9 @@ use std::{
     pin::Pin,
     sync::{
         atomic::{AtomicBool, Ordering::Relaxed},
-        mpsc::{sync_channel, Receiver, SyncSender, TryRecvError},
+        mpsc::{
+            sync_channel, Receiver, RecvTimeoutError, SyncSender, TryRecvError,
+        },
     },
     task::{Context, Poll, Waker},
     time::{Duration, Instant},
@@ -63,6 +65,25 @@ impl<'a> IntoIterator for &'a Event {
 
 type Senders = Map<usize, (Option<Waker>, SyncSender<OneShot<Option<Event>>>)>;
 
+type FilterFn = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+/// A key predicate registered with `Tree::watch`.
+struct WatchFilter(Arc<FilterFn>);
+
+impl WatchFilter {
+    fn matches(&self, key: &[u8]) -> bool {
+        (self.0)(key)
+    }
+}
+
+impl Debug for WatchFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WatchFilter")
+    }
+}
+
+type Filtered = Vec<(WatchFilter, Arc<RwLock<Senders>>)>;
+
 /// A subscriber listening on a specified prefix
 ///
 /// `Subscriber` implements both `Iterator<Item = Event>`
@@ -131,7 +152,7 @@ impl Subscriber {
     pub fn next_timeout(
         &mut self,
         mut timeout: Duration,
-    ) -> std::result::Result<Event, std::sync::mpsc::RecvTimeoutError> {
+    ) -> std::result::Result<Event, RecvTimeoutError> {
         loop {
             let before_first_receive = Instant::now();
             let mut future_rx = if let Some(future_rx) = self.existing.take() {
@@ -146,7 +167,9 @@ impl Subscriber {
             let before_second_receive = Instant::now();
             match future_rx.wait_timeout(timeout) {
                 Ok(Some(event)) => return Ok(event),
-                Ok(None) => (),
+                // the reservation was dropped because the
+                // write it was made for had to be retried
+                Ok(None) | Err(RecvTimeoutError::Disconnected) => (),
                 Err(timeout_error) => {
                     self.existing = Some(future_rx);
                     return Err(timeout_error);
@@ -213,14 +236,22 @@ impl Iterator for Subscriber {
 #[derive(Debug, Default)]
 pub(crate) struct Subscribers {
     watched: RwLock<BTreeMap<Vec<u8>, Arc<RwLock<Senders>>>>,
+    // each filtered subscriber gets its own senders, since
+    // filters can't be compared to share them like prefixes
+    filtered: RwLock<Filtered>,
     ever_used: AtomicBool,
 }
 
 impl Drop for Subscribers {
     fn drop(&mut self) {
         let watched = self.watched.read();
+        let filtered = self.filtered.read();
 
-        for senders_mu in watched.values() {
+        let all_senders = watched
+            .values()
+            .chain(filtered.iter().map(|(_, senders_mu)| senders_mu));
+
+        for senders_mu in all_senders {
             let senders = std::mem::take(&mut *senders_mu.write());
             for (_, (waker_opt, sender)) in senders {
                 drop(sender);
@@ -266,6 +297,44 @@ impl Subscribers {
         Subscriber { id, rx, existing: None, home: arc_senders.clone() }
     }
 
+    pub(crate) fn register_filter<F>(&self, filter: F) -> Subscriber
+    where
+        F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+    {
+        self.ever_used.store(true, Relaxed);
+
+        let (tx, rx) = sync_channel(1024);
+
+        let id = ID_GEN.fetch_add(1, Relaxed);
+
+        let mut senders = Map::default();
+        senders.insert(id, (None, tx));
+        let home = Arc::new(RwLock::new(senders));
+
+        let mut w_filtered = self.filtered.write();
+        // clean up after subscribers that have been dropped
+        w_filtered.retain(|(_, senders_mu)| !senders_mu.read().is_empty());
+        w_filtered.push((WatchFilter(Arc::new(filter)), home.clone()));
+
+        Subscriber { id, rx, existing: None, home }
+    }
+
+    // Returns `true` if a write to `key` may need to be
+    // reserved for at least one subscriber.
+    pub(crate) fn watches(&self, key: &[u8]) -> bool {
+        if !self.ever_used.load(Relaxed) {
+            return false;
+        }
+
+        let r_mu = self.watched.read();
+        if r_mu.keys().any(|prefix| key.starts_with(prefix)) {
+            return true;
+        }
+
+        let r_filtered = self.filtered.read();
+        r_filtered.iter().any(|(filter, _)| filter.matches(key))
+    }
+
     pub(crate) fn reserve_batch(
         &self,
         batch: &Batch,
@@ -275,28 +344,41 @@ impl Subscribers {
         }
 
         let r_mu = self.watched.read();
+        let r_filtered = self.filtered.read();
 
         let mut skip_indices = std::collections::HashSet::new();
-        let mut subscribers = vec![];
+        let mut matched = vec![];
 
         for key in batch.writes.keys() {
             for (idx, (prefix, subs_rwl)) in r_mu.iter().enumerate() {
                 if key.starts_with(prefix) && !skip_indices.contains(&idx) {
                     skip_indices.insert(idx);
-                    let subs = subs_rwl.read();
-
-                    for (_id, (waker, sender)) in subs.iter() {
-                        let (tx, rx) = OneShot::pair();
-                        if let Err(err) = sender.try_send(rx) {
-                            error!("send error: {:?}", err);
-                            continue;
-                        }
-                        subscribers.push((waker.clone(), tx));
-                    }
+                    matched.push(subs_rwl);
                 }
             }
         }
 
+        for (filter, subs_rwl) in r_filtered.iter() {
+            if batch.writes.keys().any(|key| filter.matches(key)) {
+                matched.push(subs_rwl);
+            }
+        }
+
+        let mut subscribers = vec![];
+
+        for subs_rwl in matched {
+            let subs = subs_rwl.read();
+
+            for (_id, (waker, sender)) in subs.iter() {
+                let (tx, rx) = OneShot::pair();
+                if let Err(err) = sender.try_send(rx) {
+                    error!("send error: {:?}", err);
+                    continue;
+                }
+                subscribers.push((waker.clone(), tx));
+            }
+        }
+
         if subscribers.is_empty() {
             None
         } else {
@@ -313,11 +395,20 @@ impl Subscribers {
         }
 
         let r_mu = self.watched.read();
-        let prefixes = r_mu.iter().filter(|(k, _)| key.as_ref().starts_with(k));
+        let prefixes = r_mu
+            .iter()
+            .filter(|(k, _)| key.as_ref().starts_with(k))
+            .map(|(_, subs_rwl)| subs_rwl);
+
+        let r_filtered = self.filtered.read();
+        let filtered = r_filtered
+            .iter()
+            .filter(|(filter, _)| filter.matches(key.as_ref()))
+            .map(|(_, subs_rwl)| subs_rwl);
 
         let mut subscribers = vec![];
 
-        for (_, subs_rwl) in prefixes {
+        for subs_rwl in prefixes.chain(filtered) {
             let subs = subs_rwl.read();
 
             for (_id, (waker, sender)) in subs.iter() {
@@ -353,3 +444,23 @@ impl ReservedBroadcast {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc::RecvTimeoutError;
+
+    use super::*;
+
+    #[test]
+    fn next_timeout_reports_a_pending_event_as_a_timeout() {
+        let subscribers = Subscribers::default();
+        let mut subscriber = subscribers.register(b"");
+
+        // reserved but not completed, like a write in progress
+        let reservation = subscribers.reserve(b"key");
+        assert!(reservation.is_some());
+
+        let result = subscriber.next_timeout(Duration::from_millis(10));
+        assert!(matches!(result, Err(RecvTimeoutError::Timeout)));
+    }
+}
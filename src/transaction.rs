@@ -264,9 +264,11 @@ impl TransactionalTree {
         K: AsRef<[u8]> + Into<IVec>,
         V: Into<IVec>,
     {
+        let value = value.into();
+        self.tree.check_size_limits(key.as_ref(), Some(&value))?;
         let old = self.get(key.as_ref())?;
         let mut writes = self.writes.borrow_mut();
-        writes.insert(key, value.into());
+        writes.insert(key, value);
         Ok(old)
     }
 
@@ -356,7 +358,7 @@ impl TransactionalTree {
     fn commit(&self, event: Event) -> Result<()> {
         let writes = std::mem::take(&mut *self.writes.borrow_mut());
         let mut guard = pin();
-        self.tree.apply_batch_inner(writes, Some(event), &mut guard)
+        self.tree.apply_batch_inner(writes, Some(event), None, &mut guard)
     }
 
     fn from_tree(tree: &Tree) -> Self {
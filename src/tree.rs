@@ -1,14 +1,20 @@
 use std::{
     borrow::Cow,
     fmt::{self, Debug},
+    io,
     num::NonZeroU64,
-    ops::{self, Deref, RangeBounds},
-    sync::atomic::Ordering::SeqCst,
+    ops::{self, ControlFlow, Deref, RangeBounds},
+    sync::{atomic::Ordering::SeqCst, mpsc::RecvTimeoutError},
+    time::Duration,
 };
 
 use parking_lot::RwLock;
 
-use crate::{atomic_shim::AtomicU64, pagecache::NodeView, *};
+use crate::{
+    atomic_shim::AtomicU64,
+    pagecache::{NodeView, PageView},
+    *,
+};
 
 #[derive(Debug, Clone)]
 pub(crate) struct View<'g> {
@@ -37,6 +43,31 @@ const fn out_of_bounds(numba: usize) -> bool {
     numba > MAX_BLOB
 }
 
+// Splits sorted items into groups that fill nodes to about
+// three quarters of the size at which they would be split.
+fn node_chunks<'a, 'b>(
+    items: &'a [(&'b [u8], &'b [u8])],
+) -> Vec<&'a [(&'b [u8], &'b [u8])]> {
+    const TARGET_NODE_SIZE: usize = (1024 - MAX_MSG_HEADER_LEN) * 3 / 4;
+
+    let mut ret = vec![];
+    let mut start = 0;
+    let mut size = 0;
+
+    for (idx, (k, v)) in items.iter().enumerate() {
+        let item_size = k.len() + v.len() + 2;
+        if idx > start && size + item_size > TARGET_NODE_SIZE {
+            ret.push(&items[start..idx]);
+            start = idx;
+            size = 0;
+        }
+        size += item_size;
+    }
+    ret.push(&items[start..]);
+
+    ret
+}
+
 #[cold]
 const fn bounds_error() -> Result<()> {
     Err(Error::Unsupported(
@@ -140,7 +171,20 @@ impl Deref for Tree {
 
 impl Tree {
     /// Insert a key to a new value, returning the last value if it
-    /// was set.
+    /// was set. The old value is read and replaced in a single
+    /// atomic operation, so this doubles as an unconditional swap.
+    ///
+    /// The empty key is an ordinary key that sorts before every
+    /// other key. The empty low bound of the leftmost node only
+    /// marks where that node's key range starts and is never
+    /// confused with a stored key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `Error::Io` of kind `InvalidInput` if the key
+    /// or value is larger than `Config::max_key_size` or
+    /// `Config::max_value_size`. The same limits apply to every
+    /// other write.
     ///
     /// # Examples
     ///
@@ -153,6 +197,8 @@ impl Tree {
     /// # Ok(()) }
     /// ```
     #[doc(alias = "set")]
+    #[doc(alias = "replace")]
+    #[doc(alias = "swap")]
     pub fn insert<K, V>(&self, key: K, value: V) -> Result<Option<IVec>>
     where
         K: AsRef<[u8]>,
@@ -174,6 +220,30 @@ impl Tree {
         }
     }
 
+    // Rejects a write whose key or value is larger than
+    // `Config::max_key_size` or `Config::max_value_size`.
+    pub(crate) fn check_size_limits(
+        &self,
+        key: &[u8],
+        value: Option<&[u8]>,
+    ) -> Result<()> {
+        if key.len() > self.context.max_key_size {
+            return Err(Error::Io(
+                io::ErrorKind::InvalidInput,
+                "key is larger than Config::max_key_size",
+            ));
+        }
+        match value {
+            Some(v) if v.len() > self.context.max_value_size => {
+                Err(Error::Io(
+                    io::ErrorKind::InvalidInput,
+                    "value is larger than Config::max_value_size",
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn insert_inner(
         &self,
         key: &[u8],
@@ -224,6 +294,7 @@ impl Tree {
             if out_of_bounds(value_ivec.len()) {
                 bounds_error()?;
             }
+            self.check_size_limits(key, Some(&value_ivec))?;
             Link::Set(encoded_key, value_ivec)
         } else {
             Link::Del(encoded_key)
@@ -234,6 +305,10 @@ impl Tree {
 
         if link.is_ok() {
             // success
+            self.context.pagecache.record_logical_write(
+                key.len() + value.as_ref().map_or(0, |v| v.len()),
+            );
+
             if let Some(Some(res)) = subscriber_reservation.take() {
                 let event = subscriber::Event::single_update(
                     self.clone(),
@@ -405,15 +480,426 @@ impl Tree {
     pub fn apply_batch(&self, batch: Batch) -> Result<()> {
         let _cc = concurrency_control::write();
         let mut guard = pin();
-        self.apply_batch_inner(batch, None, &mut guard)
+        self.apply_batch_inner(batch, None, None, &mut guard)
+    }
+
+    /// Atomically apply a `Batch` like [`Tree::apply_batch`],
+    /// and block until it is durable on disk. This only waits
+    /// for the log up to the point that contains the batch,
+    /// so writes that are reserved after it returns are not
+    /// flushed along with it, which makes it cheaper than
+    /// following `apply_batch` with a full [`Tree::flush`].
+    /// Returns a [`Checkpoint`] that covers the batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let mut batch = sled::Batch::default();
+    /// batch.insert("checkpoint_marker", "42");
+    ///
+    /// let checkpoint = db.apply_batch_durable(batch)?;
+    /// assert!(db.durable_at_least(&checkpoint));
+    /// # Ok(()) }
+    /// ```
+    pub fn apply_batch_durable(&self, batch: Batch) -> Result<Checkpoint> {
+        let cc = concurrency_control::write();
+        let mut guard = pin();
+        self.apply_batch_inner(batch, None, None, &mut guard)?;
+
+        // every reservation made for the batch has completed by
+        // now, so the highest reserved lsn is at or beyond it
+        let lsn = self.context.pagecache.log.max_reserved_lsn();
+        drop(guard);
+        drop(cc);
+
+        self.context.pagecache.log.make_stable(lsn)?;
+        Ok(Checkpoint { lsn })
+    }
+
+    /// Atomically apply a `Batch` like [`Tree::apply_batch`],
+    /// returning the value that each written key held right
+    /// before the batch was applied, or `None` if the key was
+    /// not present. The previous values are read while the
+    /// batch is being applied, so no other write can slip in
+    /// between reading them and applying the batch.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert("key_0", "val_0")?;
+    ///
+    /// let mut batch = sled::Batch::default();
+    /// batch.insert("key_0", "val_1");
+    /// batch.insert("key_a", "val_a");
+    ///
+    /// let old = db.apply_batch_returning_old(batch)?;
+    /// assert_eq!(old.len(), 2);
+    /// assert_eq!(old[&sled::IVec::from("key_0")], Some("val_0".into()));
+    /// assert_eq!(old[&sled::IVec::from("key_a")], None);
+    /// # Ok(()) }
+    /// ```
+    pub fn apply_batch_returning_old(
+        &self,
+        batch: Batch,
+    ) -> Result<BTreeMap<IVec, Option<IVec>>> {
+        let _cc = concurrency_control::write();
+        let mut guard = pin();
+        let mut old_values = BTreeMap::new();
+        self.apply_batch_inner(batch, None, Some(&mut old_values), &mut guard)?;
+        Ok(old_values)
+    }
+
+    /// Atomically apply a `Batch` only if the value stored for
+    /// `guard_key` matches `expected`. This is useful for
+    /// lease-style coordination, where a batch should only
+    /// be written while a "lock" key still holds our token.
+    /// If `expected` is `None`, the batch will only be applied
+    /// if `guard_key` is not present.
+    ///
+    /// It returns `Ok(Ok(()))` if the batch was applied, and
+    /// `Ok(Err(CompareAndSwapError))` containing the current
+    /// value of `guard_key` if the condition did not hold, in
+    /// which case none of the batch was applied. The `proposed`
+    /// field of the error is set to the batch's write for
+    /// `guard_key`, if the batch contains one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert("lease", "token_a")?;
+    ///
+    /// let mut batch = sled::Batch::default();
+    /// batch.insert("key_a", "val_a");
+    ///
+    /// // the lease has been taken over by someone else
+    /// let res = db.apply_batch_if("lease", Some("token_b"), batch.clone())?;
+    /// assert_eq!(res.unwrap_err().current, Some(sled::IVec::from("token_a")));
+    /// assert_eq!(db.get("key_a")?, None);
+    ///
+    /// // we still hold the lease
+    /// db.apply_batch_if("lease", Some("token_a"), batch)?.unwrap();
+    /// assert_eq!(db.get("key_a")?, Some(sled::IVec::from("val_a")));
+    /// # Ok(()) }
+    /// ```
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn apply_batch_if<K, V>(
+        &self,
+        guard_key: K,
+        expected: Option<V>,
+        batch: Batch,
+    ) -> CompareAndSwapResult
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let _cc = concurrency_control::write();
+        let mut guard = pin();
+
+        let current = loop {
+            if let Ok(current) =
+                self.get_inner(guard_key.as_ref(), &mut guard)?
+            {
+                break current;
+            }
+        };
+
+        let matches = match (expected.as_ref(), &current) {
+            (None, None) => true,
+            (Some(e), Some(c)) => e.as_ref() == &**c,
+            _ => false,
+        };
+
+        if !matches {
+            let proposed = batch.get(guard_key).and_then(|v| v.cloned());
+            return Ok(Err(CompareAndSwapError { current, proposed }));
+        }
+
+        self.apply_batch_inner(batch, None, None, &mut guard)?;
+
+        Ok(Ok(()))
+    }
+
+    /// Atomically apply a `ConditionalBatch`, whose writes
+    /// are only applied if every key still holds the value
+    /// its write expects.
+    ///
+    /// It returns `Ok(Ok(()))` if the batch was applied, and
+    /// otherwise `Ok(Err(mismatches))`, in which case none of
+    /// the batch was applied. `mismatches` holds every key
+    /// whose condition failed along with its current value,
+    /// sorted by key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert("a", "1")?;
+    /// db.insert("b", "1")?;
+    ///
+    /// let mut batch = sled::ConditionalBatch::default();
+    /// batch.insert_if("a", Some("1"), "2");
+    /// batch.remove_if("b", Some("2"));
+    ///
+    /// let mismatches = db.apply_conditional_batch(batch)?.unwrap_err();
+    /// assert_eq!(mismatches, vec![("b".into(), Some("1".into()))]);
+    /// assert_eq!(db.get("a")?, Some(sled::IVec::from("1")));
+    /// # Ok(()) }
+    /// ```
+    pub fn apply_conditional_batch(
+        &self,
+        batch: ConditionalBatch,
+    ) -> ConditionalBatchResult {
+        let _cc = concurrency_control::write();
+        let mut guard = pin();
+
+        let mut mismatches = vec![];
+        for (key, expected) in &batch.expected {
+            let current = loop {
+                if let Ok(current) = self.get_inner(key, &mut guard)? {
+                    break current;
+                }
+            };
+
+            if current != *expected {
+                mismatches.push((key.clone(), current));
+            }
+        }
+
+        if !mismatches.is_empty() {
+            mismatches.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+            return Ok(Err(mismatches));
+        }
+
+        self.apply_batch_inner(batch.batch, None, None, &mut guard)?;
+
+        Ok(Ok(()))
+    }
+
+    /// Loads sorted key-value pairs into an empty `Tree` by
+    /// building its nodes directly at high occupancy, instead
+    /// of inserting keys one at a time and growing the tree
+    /// through splits. The whole load is recovered atomically,
+    /// and the number of loaded pairs is returned.
+    ///
+    /// All pairs are buffered in memory before the nodes are
+    /// built, and subscribers are not notified of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the `Tree` is not
+    /// empty, or if the keys are not strictly increasing,
+    /// in which case nothing is written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let pairs =
+    ///     (0_u32..1000).map(|i| (i.to_be_bytes(), i.to_le_bytes().to_vec()));
+    /// assert_eq!(db.load_sorted(pairs)?, 1000);
+    ///
+    /// assert_eq!(db.len(), 1000);
+    /// let value = db.get(7_u32.to_be_bytes())?.unwrap();
+    /// assert_eq!(value, 7_u32.to_le_bytes());
+    ///
+    /// // the tree is no longer empty
+    /// assert!(db.load_sorted(vec![(b"a", b"a")]).is_err());
+    /// # Ok(()) }
+    /// ```
+    pub fn load_sorted<I, K, V>(&self, pairs: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<[u8]>,
+        V: Into<IVec>,
+    {
+        let mut items: Vec<(IVec, IVec)> = vec![];
+
+        for (k, v) in pairs {
+            let (k, v) = (IVec::from(k.as_ref()), v.into());
+            if out_of_bounds(k.len()) || out_of_bounds(v.len()) {
+                bounds_error()?;
+            }
+            self.check_size_limits(&k, Some(&v))?;
+            if let Some((last, _)) = items.last() {
+                if *last >= k {
+                    return Err(Error::Unsupported(
+                        "load_sorted requires strictly increasing keys",
+                    ));
+                }
+            }
+            items.push((k, v));
+        }
+
+        let _cc = concurrency_control::write();
+        let guard = pin();
+
+        let root_pid = self.root.load(Acquire);
+        let root_view = self
+            .view_for_pid(root_pid, &guard)?
+            .expect("the root of a tree must exist");
+        let not_empty =
+            Err(Error::Unsupported("load_sorted requires an empty tree"));
+
+        let mut root_children = root_view.iter_index_pids();
+        let leaf_pid = match (root_children.next(), root_children.next()) {
+            (Some(leaf_pid), None) => leaf_pid,
+            _ => return not_empty,
+        };
+        let leaf_view = self
+            .view_for_pid(leaf_pid, &guard)?
+            .expect("the only child of the root must exist");
+        if leaf_view.successor(&ops::Bound::Unbounded).is_some() {
+            return not_empty;
+        }
+
+        if items.is_empty() {
+            return Ok(0);
+        }
+
+        let peg = self.context.pin_log(&guard)?;
+
+        // every page allocated below is freed again if a later
+        // step fails, so that an error does not leak them
+        let mut allocated: Vec<(PageId, PageView<'_>)> = vec![];
+        let built = (|| {
+            // build the leaves, then each level of index nodes
+            // above them, until a single node remains to become
+            // the new root
+            let leaf_items: Vec<(&[u8], &[u8])> =
+                items.iter().map(|(k, v)| (&**k, &**v)).collect();
+            let mut level = self.allocate_level(
+                &leaf_items,
+                false,
+                &mut allocated,
+                &guard,
+            )?;
+
+            let root = loop {
+                let pid_bytes: Vec<[u8; 8]> =
+                    level.iter().map(|(_, pid)| pid.to_le_bytes()).collect();
+                let index_items: Vec<(&[u8], &[u8])> = level
+                    .iter()
+                    .zip(pid_bytes.iter())
+                    .map(|((lo, _), pid)| (&**lo, &pid[..]))
+                    .collect();
+
+                if node_chunks(&index_items).len() == 1 {
+                    let lo: &[u8] = &[];
+                    break Node::new_from_sorted(
+                        lo,
+                        None,
+                        true,
+                        None,
+                        &index_items,
+                    );
+                }
+
+                level = self.allocate_level(
+                    &index_items,
+                    true,
+                    &mut allocated,
+                    &guard,
+                )?;
+            };
+
+            let replace = self.context.pagecache.replace(
+                root_pid,
+                root_view.node_view.0,
+                &root,
+                &guard,
+            )?;
+            if replace.is_err() {
+                return Err(Error::ReportableBug(
+                    "the root changed while holding exclusive access",
+                ));
+            }
+
+            Ok(())
+        })();
+
+        if let Err(e) = built {
+            // report the error that stopped the load rather than
+            // any that freeing runs into
+            for (pid, view) in allocated {
+                let _ = self.context.pagecache.free(pid, view, &guard);
+            }
+            return Err(e);
+        }
+
+        self.context.pagecache.record_logical_write(
+            items.iter().map(|(k, v)| k.len() + v.len()).sum(),
+        );
+
+        let _ = self.context.pagecache.free(
+            leaf_pid,
+            leaf_view.node_view.0,
+            &guard,
+        )?;
+
+        peg.seal_batch()?;
+
+        Ok(items.len())
+    }
+
+    // Allocates one level of nodes for `load_sorted`, right to
+    // left so that each node can point to its right sibling,
+    // and returns the lo key and pid of each node in order.
+    // Every allocated page is also added to `allocated`.
+    fn allocate_level<'g>(
+        &self,
+        items: &[(&[u8], &[u8])],
+        is_index: bool,
+        allocated: &mut Vec<(PageId, PageView<'g>)>,
+        guard: &'g Guard,
+    ) -> Result<Vec<(IVec, PageId)>> {
+        let chunks = node_chunks(items);
+        let mut ret = Vec::with_capacity(chunks.len());
+        let mut next = None;
+
+        for (idx, chunk) in chunks.iter().enumerate().rev() {
+            let lo: &[u8] = if idx == 0 { &[] } else { chunk[0].0 };
+            let hi = chunks.get(idx + 1).map(|c| c[0].0);
+
+            let node = Node::new_from_sorted(lo, hi, is_index, next, chunk);
+            let (pid, view) = self.context.pagecache.allocate(node, guard)?;
+            allocated.push((pid, view));
+
+            next = NonZeroU64::new(pid);
+            ret.push((IVec::from(lo), pid));
+        }
+
+        ret.reverse();
+        Ok(ret)
     }
 
     pub(crate) fn apply_batch_inner(
         &self,
         batch: Batch,
         transaction_batch_opt: Option<Event>,
+        mut old_values_opt: Option<&mut BTreeMap<IVec, Option<IVec>>>,
         guard: &mut Guard,
     ) -> Result<()> {
+        // check every write before applying any of them, so that
+        // an oversized write can't leave the batch half-applied
+        for (k, v_opt) in &batch.writes {
+            if let Some(v) = v_opt {
+                self.check_size_limits(k, Some(v))?;
+            }
+        }
+
         let peg_opt = if transaction_batch_opt.is_none() {
             Some(self.context.pin_log(guard)?)
         } else {
@@ -426,15 +912,15 @@ impl Tree {
 
         for (k, v_opt) in &batch.writes {
             loop {
-                if self
-                    .insert_inner(
-                        k,
-                        v_opt.clone(),
-                        transaction_batch_opt.is_some(),
-                        guard,
-                    )?
-                    .is_ok()
-                {
+                if let Ok(old) = self.insert_inner(
+                    k,
+                    v_opt.clone(),
+                    transaction_batch_opt.is_some(),
+                    guard,
+                )? {
+                    if let Some(old_values) = old_values_opt.as_mut() {
+                        old_values.insert(k.clone(), old);
+                    }
                     break;
                 }
             }
@@ -485,7 +971,14 @@ impl Tree {
     /// without making a new allocation. This effectively
     /// "pushes" your provided code to the data without ever copying
     /// the data, rather than "pulling" a copy of the data to whatever code
-    /// is calling `get`.
+    /// is calling `get`. For large values this avoids the
+    /// allocation and copy that `get` performs.
+    ///
+    /// The closure runs while the tree is still being read,
+    /// so it must not call back into this `Tree` or any other
+    /// `Tree` opened from the same `Db`. Operations that need
+    /// exclusive access, like transactions, will deadlock
+    /// when called from inside the closure.
     ///
     /// # Examples
     ///
@@ -503,6 +996,7 @@ impl Tree {
     /// db.get_zero_copy(&[1], |value_opt| assert!(value_opt.is_none()));
     /// # Ok(()) }
     /// ```
+    #[doc(alias = "get_with")]
     pub fn get_zero_copy<K: AsRef<[u8]>, B, F: FnOnce(Option<&[u8]>) -> B>(
         &self,
         key: K,
@@ -525,25 +1019,11 @@ impl Tree {
         Ok(ret)
     }
 
-    pub(crate) fn get_inner(
-        &self,
-        key: &[u8],
-        guard: &mut Guard,
-    ) -> Result<Conflictable<Option<IVec>>> {
-        #[cfg(feature = "metrics")]
-        let _measure = Measure::new(&M.tree_get);
-
-        trace!("getting key {:?}", key);
-
-        let View { node_view, .. } = self.view_for_key(key.as_ref(), guard)?;
-
-        let pair = node_view.node_kv_pair(key.as_ref());
-        let val = pair.1.map(IVec::from);
-
-        Ok(Ok(val))
-    }
-
-    /// Delete a value, returning the old value if it existed.
+    /// Returns the CRC32 of the value stored for a key,
+    /// or `None` if the key is not present. The digest is
+    /// computed over the stored bytes in place, so the value
+    /// is never copied out, which makes this a cheap way to
+    /// compare a single key across replicas.
     ///
     /// # Examples
     ///
@@ -551,41 +1031,26 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// db.insert(&[1], vec![1]);
-    /// assert_eq!(db.remove(&[1]), Ok(Some(sled::IVec::from(vec![1]))));
-    /// assert_eq!(db.remove(&[1]), Ok(None));
+    /// db.insert(&[0], vec![0; 1024])?;
+    /// db.insert(&[1], vec![0; 1024])?;
+    /// db.insert(&[2], vec![1; 1024])?;
+    ///
+    /// assert_eq!(db.value_checksum(&[0])?, db.value_checksum(&[1])?);
+    /// assert_ne!(db.value_checksum(&[0])?, db.value_checksum(&[2])?);
+    /// assert_eq!(db.value_checksum(&[3])?, None);
     /// # Ok(()) }
     /// ```
-    #[doc(alias = "delete")]
-    #[doc(alias = "del")]
-    pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
-        let mut guard = pin();
-        let _cc = concurrency_control::read();
-        loop {
-            trace!("removing key {:?}", key.as_ref());
-
-            if let Ok(res) =
-                self.insert_inner(key.as_ref(), None, false, &mut guard)?
-            {
-                return Ok(res);
-            }
-        }
+    pub fn value_checksum<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+    ) -> Result<Option<u32>> {
+        self.get_zero_copy(key, |value_opt| value_opt.map(crc32fast::hash))
     }
 
-    /// Compare and swap. Capable of unique creation, conditional modification,
-    /// or deletion. If old is `None`, this will only set the value if it
-    /// doesn't exist yet. If new is `None`, will delete the value if old is
-    /// correct. If both old and new are `Some`, will modify the value if
-    /// old is correct.
-    ///
-    /// It returns `Ok(Ok(()))` if operation finishes successfully.
-    ///
-    /// If it fails it returns:
-    ///     - `Ok(Err(CompareAndSwapError(current, proposed)))` if operation
-    ///       failed to setup a new value. `CompareAndSwapError` contains
-    ///       current and proposed values.
-    ///     - `Err(Error::Unsupported)` if the database is opened in read-only
-    ///       mode.
+    /// Returns the CRC32 of all keys and values in the provided
+    /// range, hashed in key order. The checksum of the full range
+    /// of a `Tree` is the same as the part that `Db::checksum`
+    /// computes for it, minus the tree's name.
     ///
     /// # Examples
     ///
@@ -593,368 +1058,440 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// // unique creation
-    /// assert_eq!(
-    ///     db.compare_and_swap(&[1], None as Option<&[u8]>, Some(&[10])),
-    ///     Ok(Ok(()))
-    /// );
+    /// db.insert(&[1], vec![1])?;
+    /// db.insert(&[2], vec![2])?;
+    /// db.insert(&[3], vec![3])?;
     ///
-    /// // conditional modification
-    /// assert_eq!(
-    ///     db.compare_and_swap(&[1], Some(&[10]), Some(&[20])),
-    ///     Ok(Ok(()))
-    /// );
-    ///
-    /// // failed conditional modification -- the current value is returned in
-    /// // the error variant
-    /// let operation = db.compare_and_swap(&[1], Some(&[30]), Some(&[40]));
-    /// assert!(operation.is_ok()); // the operation succeeded
-    /// let modification = operation.unwrap();
-    /// assert!(modification.is_err());
-    /// let actual_value = modification.unwrap_err();
-    /// assert_eq!(actual_value.current.map(|ivec| ivec.to_vec()), Some(vec![20]));
+    /// let mut hasher = crc32fast::Hasher::new();
+    /// hasher.update(&[2]);
+    /// hasher.update(&[2]);
+    /// hasher.update(&[3]);
+    /// hasher.update(&[3]);
     ///
-    /// // conditional deletion
-    /// assert_eq!(
-    ///     db.compare_and_swap(&[1], Some(&[20]), None as Option<&[u8]>),
-    ///     Ok(Ok(()))
-    /// );
-    /// assert_eq!(db.get(&[1]), Ok(None));
+    /// let start: &[u8] = &[2];
+    /// assert_eq!(db.checksum_range(start..)?, hasher.finalize());
     /// # Ok(()) }
     /// ```
-    #[allow(clippy::needless_pass_by_value)]
-    #[doc(alias = "cas")]
-    #[doc(alias = "tas")]
-    #[doc(alias = "test_and_swap")]
-    #[doc(alias = "compare_and_set")]
-    pub fn compare_and_swap<K, OV, NV>(
-        &self,
-        key: K,
-        old: Option<OV>,
-        new: Option<NV>,
-    ) -> CompareAndSwapResult
+    pub fn checksum_range<K, R>(&self, range: R) -> Result<u32>
     where
         K: AsRef<[u8]>,
-        OV: AsRef<[u8]>,
-        NV: Into<IVec>,
+        R: RangeBounds<K>,
     {
-        trace!("cas'ing key {:?}", key.as_ref());
-        #[cfg(feature = "metrics")]
-        let _measure = Measure::new(&M.tree_cas);
+        Ok(self.range_hasher(self.range(range))?.finalize())
+    }
 
-        let guard = pin();
-        let _cc = concurrency_control::read();
+    fn range_hasher(&self, iter: Iter) -> Result<crc32fast::Hasher> {
+        let mut hasher = crc32fast::Hasher::new();
 
-        let new2 = new.map(Into::into);
+        for kv_res in iter {
+            let (k, v) = kv_res?;
+            hasher.update(&k);
+            hasher.update(&v);
+        }
 
-        // we need to retry caps until old != cur, since just because
-        // cap fails it doesn't mean our value was changed.
-        loop {
-            let View { pid, node_view, .. } =
-                self.view_for_key(key.as_ref(), &guard)?;
+        Ok(hasher)
+    }
 
-            let (encoded_key, current_value) =
-                node_view.node_kv_pair(key.as_ref());
-            let matches = match (old.as_ref(), &current_value) {
-                (None, None) => true,
-                (Some(o), Some(c)) => o.as_ref() == &**c,
-                _ => false,
-            };
+    /// Hashes the whole tree like `checksum_range(..)` does, but
+    /// splits the keyspace at the children of the root node and
+    /// hashes each part on the threadpool. The CRCs of the parts
+    /// are combined in key order, so the result is the same as
+    /// hashing the tree serially.
+    pub(crate) fn parallel_hasher(&self) -> Result<crc32fast::Hasher> {
+        let mut parts = vec![];
+        let mut lo = ops::Bound::Unbounded;
+
+        for separator in self.root_separators()? {
+            let hi = ops::Bound::Excluded(separator.clone());
+            parts.push((lo, hi));
+            lo = ops::Bound::Included(separator);
+        }
+        parts.push((lo, ops::Bound::Unbounded));
 
-            if !matches {
-                return Ok(Err(CompareAndSwapError {
-                    current: current_value.map(IVec::from),
-                    proposed: new2,
-                }));
-            }
+        let workers: Vec<_> = parts
+            .into_iter()
+            .map(|range| {
+                let tree = self.clone();
+                threadpool::spawn(move || tree.range_hasher(tree.range(range)))
+            })
+            .collect();
 
-            if current_value == new2.as_ref().map(AsRef::as_ref) {
-                // short-circuit no-op write. this is still correct
-                // because we verified that the input matches, so
-                // doing the work has the same semantic effect as not
-                // doing it in this case.
-                return Ok(Ok(()));
-            }
+        let mut hasher = crc32fast::Hasher::new();
 
-            let mut subscriber_reservation = self.subscribers.reserve(&key);
+        for worker in workers {
+            let part = worker.wait().ok_or(Error::ReportableBug(
+                "threadpool failed to complete \
+                 checksum before shutdown",
+            ))??;
+            hasher.combine(&part);
+        }
 
-            let frag = if let Some(ref new3) = new2 {
-                Link::Set(encoded_key, new3.clone())
-            } else {
-                Link::Del(encoded_key)
-            };
-            let link =
-                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+        Ok(hasher)
+    }
 
-            if link.is_ok() {
-                if let Some(res) = subscriber_reservation.take() {
-                    let event = subscriber::Event::single_update(
-                        self.clone(),
-                        key.as_ref().into(),
-                        new2,
-                    );
+    // Returns the keys that separate the children of the root
+    // node, or nothing if the root is a leaf.
+    fn root_separators(&self) -> Result<Vec<IVec>> {
+        let guard = pin();
+        let _cc = concurrency_control::read();
 
-                    res.complete(&event);
-                }
+        let root_pid = self.root.load(Acquire);
+        let root = match self.view_for_pid(root_pid, &guard)? {
+            Some(view) if view.is_index => view,
+            _ => return Ok(vec![]),
+        };
 
-                return Ok(Ok(()));
-            }
-            #[cfg(feature = "metrics")]
-            M.tree_looped();
+        let mut separators = vec![];
+        let mut bound = ops::Bound::Unbounded;
+
+        while let Some((key, _)) = root.successor(&bound) {
+            bound = ops::Bound::Excluded(key.clone());
+            separators.push(key);
+        }
+
+        // the first child starts at the lowest possible key
+        if !separators.is_empty() {
+            separators.remove(0);
         }
+
+        Ok(separators)
     }
 
-    /// Fetch the value, apply a function to it and return the result.
-    ///
-    /// # Note
-    ///
-    /// This may call the function multiple times if the value has been
-    /// changed from other threads in the meantime.
+    /// Retrieve a value from the `Tree` without recording the
+    /// access in the page cache. Unlike [`Tree::get`], this never
+    /// causes other pages to be evicted, and a node that has to
+    /// be read from disk is not kept in the cache afterwards.
+    /// This is meant for background work like integrity scans
+    /// that should not disturb the cache used by foreground reads.
     ///
     /// # Examples
     ///
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use sled::{Config, Error, IVec};
-    /// use std::convert::TryInto;
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(&[0], vec![0])?;
+    /// assert_eq!(db.get_cold(&[0]), Ok(Some(sled::IVec::from(vec![0]))));
+    /// assert_eq!(db.get_cold(&[1]), Ok(None));
+    /// # Ok(()) }
+    /// ```
+    pub fn get_cold<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_get);
+
+        trace!("getting key {:?} without touching the cache", key.as_ref());
+
+        let View { node_view, .. } =
+            self.view_for_key_inner(key.as_ref(), true, &guard)?;
+
+        let pair = node_view.node_kv_pair(key.as_ref());
+
+        Ok(pair.1.map(IVec::from))
+    }
+
+    /// Create a double-ended iterator over a range of the `Tree`,
+    /// like [`Tree::range`], that reads without recording its
+    /// accesses in the page cache. See [`Tree::get_cold`].
     ///
-    /// let config = Config::new().temporary(true);
-    /// let db = config.open()?;
+    /// # Examples
     ///
-    /// fn u64_to_ivec(number: u64) -> IVec {
-    ///     IVec::from(number.to_be_bytes().to_vec())
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..10_u8 {
+    ///     db.insert(&[i], vec![i])?;
     /// }
     ///
-    /// let zero = u64_to_ivec(0);
-    /// let one = u64_to_ivec(1);
-    /// let two = u64_to_ivec(2);
-    /// let three = u64_to_ivec(3);
-    ///
-    /// fn increment(old: Option<&[u8]>) -> Option<Vec<u8>> {
-    ///     let number = match old {
-    ///         Some(bytes) => {
-    ///             let array: [u8; 8] = bytes.try_into().unwrap();
-    ///             let number = u64::from_be_bytes(array);
-    ///             number + 1
-    ///         }
-    ///         None => 0,
-    ///     };
-    ///
-    ///     Some(number.to_be_bytes().to_vec())
+    /// let mut count = 0;
+    /// for kv in db.scan_cold::<&[u8], _>(..) {
+    ///     let (k, v) = kv?;
+    ///     assert_eq!(k, v);
+    ///     count += 1;
     /// }
-    ///
-    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(zero)));
-    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(one)));
-    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(two)));
-    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(three)));
+    /// assert_eq!(count, 10);
     /// # Ok(()) }
     /// ```
-    pub fn update_and_fetch<K, V, F>(
-        &self,
-        key: K,
-        mut f: F,
-    ) -> Result<Option<IVec>>
+    pub fn scan_cold<K, R>(&self, range: R) -> Iter
     where
         K: AsRef<[u8]>,
-        F: FnMut(Option<&[u8]>) -> Option<V>,
-        V: Into<IVec>,
+        R: RangeBounds<K>,
     {
-        let key_ref = key.as_ref();
-        let mut current = self.get(key_ref)?;
-
-        loop {
-            let tmp = current.as_ref().map(AsRef::as_ref);
-            let next = f(tmp).map(Into::into);
-            match self.compare_and_swap::<_, _, IVec>(
-                key_ref,
-                tmp,
-                next.clone(),
-            )? {
-                Ok(()) => return Ok(next),
-                Err(CompareAndSwapError { current: cur, .. }) => {
-                    current = cur;
-                }
-            }
-        }
+        let mut iter = self.range(range);
+        iter.cold = true;
+        iter
     }
 
-    /// Fetch the value, apply a function to it and return the previous value.
-    ///
-    /// # Note
+    /// Read the leaves that hold `range` into the page cache,
+    /// so that later operations on it do not have to wait for
+    /// the disk. Returns the in-memory size in bytes of the
+    /// leaves that were visited, including any that were
+    /// already cached.
     ///
-    /// This may call the function multiple times if the value has been
-    /// changed from other threads in the meantime.
+    /// Warming stops once `cache_capacity` bytes of leaves have
+    /// been visited, since reading any more would only evict
+    /// the leaves read at the start of the range. See
+    /// `Config::warm_on_open` for warming while opening.
     ///
     /// # Examples
     ///
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use sled::{Config, Error, IVec};
-    /// use std::convert::TryInto;
-    ///
-    /// let config = Config::new().temporary(true);
-    /// let db = config.open()?;
-    ///
-    /// fn u64_to_ivec(number: u64) -> IVec {
-    ///     IVec::from(number.to_be_bytes().to_vec())
-    /// }
-    ///
-    /// let zero = u64_to_ivec(0);
-    /// let one = u64_to_ivec(1);
-    /// let two = u64_to_ivec(2);
-    ///
-    /// fn increment(old: Option<&[u8]>) -> Option<Vec<u8>> {
-    ///     let number = match old {
-    ///         Some(bytes) => {
-    ///             let array: [u8; 8] = bytes.try_into().unwrap();
-    ///             let number = u64::from_be_bytes(array);
-    ///             number + 1
-    ///         }
-    ///         None => 0,
-    ///     };
-    ///
-    ///     Some(number.to_be_bytes().to_vec())
-    /// }
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"user/1", vec![1])?;
     ///
-    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(None));
-    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(Some(zero)));
-    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(Some(one)));
-    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(Some(two)));
+    /// assert!(db.warm(&b"user/"[..]..&b"user0"[..])? > 0);
     /// # Ok(()) }
     /// ```
-    pub fn fetch_and_update<K, V, F>(
-        &self,
-        key: K,
-        mut f: F,
-    ) -> Result<Option<IVec>>
+    pub fn warm<K, R>(&self, range: R) -> Result<u64>
     where
         K: AsRef<[u8]>,
-        F: FnMut(Option<&[u8]>) -> Option<V>,
-        V: Into<IVec>,
+        R: RangeBounds<K>,
     {
-        let key_ref = key.as_ref();
-        let mut current = self.get(key_ref)?;
-
-        loop {
-            let tmp = current.as_ref().map(AsRef::as_ref);
-            let next = f(tmp);
-            match self.compare_and_swap(key_ref, tmp, next)? {
-                Ok(()) => return Ok(current),
-                Err(CompareAndSwapError { current: cur, .. }) => {
-                    current = cur;
-                }
-            }
-        }
+        let Iter { lo, hi, .. } = self.range(range);
+        self.warm_inner(lo, hi, self.context.cache_capacity as u64)
     }
 
-    /// Subscribe to `Event`s that happen to keys that have
-    /// the specified prefix. Events for particular keys are
-    /// guaranteed to be witnessed in the same order by all
-    /// threads, but threads may witness different interleavings
-    /// of `Event`s across different keys. If subscribers don't
-    /// keep up with new writes, they will cause new writes
-    /// to block. There is a buffer of 1024 items per
-    /// `Subscriber`. This can be used to build reactive
-    /// and replicated systems.
-    ///
-    /// `Subscriber` implements both `Iterator<Item = Event>`
-    /// and `Future<Output=Option<Event>>`
+    /// Pages out the leaves that hold `range`, dropping them
+    /// from memory so that each one is read back from disk the
+    /// next time it is used. Leaves that are not resident are
+    /// not read in to find the next one. Returns the number of
+    /// leaves that were paged out. See [`Db::page_out_all`].
     ///
     /// # Examples
     ///
-    /// Synchronous, blocking subscriber:
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// // watch all events by subscribing to the empty prefix
-    /// let mut subscriber = db.watch_prefix(vec![]);
+    /// db.insert(b"a", vec![0; 100])?;
     ///
-    /// let tree_2 = db.clone();
-    /// let thread = std::thread::spawn(move || {
-    ///     db.insert(vec![0], vec![1])
-    /// });
-    ///
-    /// // `Subscription` implements `Iterator<Item=Event>`
-    /// for event in subscriber.take(1) {
-    ///     // Events occur due to single key operations,
-    ///     // batches, or transactions. The tree is included
-    ///     // so that you may perform a new transaction or
-    ///     // operation in response to the event.
-    ///     for (tree, key, value_opt) in &event {
-    ///         if let Some(value) = value_opt {
-    ///             // key `key` was set to value `value`
-    ///         } else {
-    ///             // key `key` was removed
-    ///         }
-    ///     }
-    /// }
-    ///
-    /// # thread.join().unwrap();
+    /// assert_eq!(db.page_out::<&[u8], _>(..)?, 1);
+    /// assert_eq!(db.page_out::<&[u8], _>(..)?, 0);
+    /// assert_eq!(db.get(b"a")?, Some(vec![0; 100].into()));
     /// # Ok(()) }
     /// ```
-    /// Asynchronous, non-blocking subscriber:
+    pub fn page_out<K, R>(&self, range: R) -> Result<usize>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let Iter { lo, hi, .. } = self.range(range);
+        let mut cursor = match lo {
+            ops::Bound::Included(start) | ops::Bound::Excluded(start) => start,
+            ops::Bound::Unbounded => IVec::default(),
+        };
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let mut to_evict = vec![];
+        loop {
+            let view = self.view_for_key_inner(&cursor, true, &guard)?;
+            if self.context.pagecache.get_resident(view.pid, &guard).is_some() {
+                to_evict.push(view.pid);
+            }
+
+            if view.contains_upper_bound(&hi) {
+                break;
+            }
+            cursor = IVec::from(view.hi().unwrap());
+        }
+
+        let paged_out = to_evict.len();
+        self.context.pagecache.page_out(to_evict, &guard)?;
+        Ok(paged_out)
+    }
+
+    // Visits each leaf overlapping `lo` and `hi`, in order,
+    // until `budget` bytes of leaves have been visited.
+    pub(crate) fn warm_inner(
+        &self,
+        lo: ops::Bound<IVec>,
+        hi: ops::Bound<IVec>,
+        budget: u64,
+    ) -> Result<u64> {
+        let mut cursor = match lo {
+            ops::Bound::Included(start) | ops::Bound::Excluded(start) => start,
+            ops::Bound::Unbounded => IVec::default(),
+        };
+
+        let mut warmed = 0;
+        while warmed < budget {
+            let guard = pin();
+            let _cc = concurrency_control::read();
+            let view = self.view_for_key(&cursor, &guard)?;
+            warmed += view.rss();
+
+            if view.contains_upper_bound(&hi) {
+                break;
+            }
+            cursor = IVec::from(view.hi().unwrap());
+        }
+
+        Ok(warmed)
+    }
+
+    /// Retrieve the values for several keys at once. The
+    /// returned values are in the same order as the provided
+    /// keys. Keys are looked up in sorted order internally, so
+    /// keys that land on the same node are served from a single
+    /// traversal of the tree.
     ///
-    /// `Subscription` implements `Future<Output=Option<Event>>`.
+    /// # Examples
     ///
     /// ```
-    /// # async fn foo() {
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
-    /// # let db = config.open().unwrap();
-    /// # let mut subscriber = db.watch_prefix(vec![]);
-    /// while let Some(event) = (&mut subscriber).await {
-    ///     /* use it */
-    /// }
-    /// # }
+    /// # let db = config.open()?;
+    /// db.insert(&[1], vec![10])?;
+    /// db.insert(&[3], vec![30])?;
+    ///
+    /// let values = db.multi_get(vec![&[3], &[2], &[1], &[3]])?;
+    /// assert_eq!(
+    ///     values,
+    ///     vec![
+    ///         Some(sled::IVec::from(&[30])),
+    ///         None,
+    ///         Some(sled::IVec::from(&[10])),
+    ///         Some(sled::IVec::from(&[30])),
+    ///     ]
+    /// );
+    /// # Ok(()) }
     /// ```
-    pub fn watch_prefix<P: AsRef<[u8]>>(&self, prefix: P) -> Subscriber {
-        self.subscribers.register(prefix.as_ref())
+    pub fn multi_get<K, I>(&self, keys: I) -> Result<Vec<Option<IVec>>>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        let keys: Vec<IVec> =
+            keys.into_iter().map(|k| IVec::from(k.as_ref())).collect();
+
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let found = {
+            let guard = pin();
+            let _cc = concurrency_control::read();
+            self.get_sorted_inner(sorted, &guard)?
+        };
+
+        let ret = keys
+            .iter()
+            .map(|key| {
+                let idx = found
+                    .binary_search_by(|(k, _)| k.cmp(key))
+                    .expect("every requested key is looked up");
+                found[idx].1.clone()
+            })
+            .collect();
+
+        Ok(ret)
     }
 
-    /// Synchronously flushes all dirty IO buffers and calls
-    /// fsync. If this succeeds, it is guaranteed that all
-    /// previous writes will be recovered if the system
-    /// crashes. Returns the number of bytes flushed during
-    /// this call.
+    /// Retrieve the values for several keys at once, returning
+    /// `(key, value)` pairs sorted in ascending key order with
+    /// repeated keys removed. Keys that land on the same node
+    /// are served from a single traversal of the tree.
     ///
-    /// Flushing can take quite a lot of time, and you should
-    /// measure the performance impact of using it on
-    /// realistic sustained workloads running on realistic
-    /// hardware.
+    /// # Examples
     ///
-    /// This is called automatically on drop.
-    pub fn flush(&self) -> Result<usize> {
-        self.context.pagecache.flush()
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(&[1], vec![10])?;
+    /// db.insert(&[3], vec![30])?;
+    ///
+    /// let results = db.multi_get_sorted(vec![&[3], &[2], &[1], &[3]])?;
+    /// assert_eq!(
+    ///     results,
+    ///     vec![
+    ///         (sled::IVec::from(&[1]), Some(sled::IVec::from(&[10]))),
+    ///         (sled::IVec::from(&[2]), None),
+    ///         (sled::IVec::from(&[3]), Some(sled::IVec::from(&[30]))),
+    ///     ]
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn multi_get_sorted<K, I>(
+        &self,
+        keys: I,
+    ) -> Result<Vec<(IVec, Option<IVec>)>>
+    where
+        K: AsRef<[u8]>,
+        I: IntoIterator<Item = K>,
+    {
+        let mut keys: Vec<IVec> =
+            keys.into_iter().map(|k| IVec::from(k.as_ref())).collect();
+        keys.sort_unstable();
+        keys.dedup();
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        self.get_sorted_inner(keys, &guard)
     }
 
-    /// Asynchronously flushes all dirty IO buffers
-    /// and calls fsync. If this succeeds, it is
-    /// guaranteed that all previous writes will
-    /// be recovered if the system crashes. Returns
-    /// the number of bytes flushed during this call.
-    ///
-    /// Flushing can take quite a lot of time, and you
-    /// should measure the performance impact of
-    /// using it on realistic sustained workloads
-    /// running on realistic hardware.
-    // this clippy check is miss-firing on async code.
-    #[allow(clippy::used_underscore_binding)]
-    #[allow(clippy::shadow_same)]
-    pub async fn flush_async(&self) -> Result<usize> {
-        let pagecache = self.context.pagecache.clone();
-        if let Some(result) = threadpool::spawn(move || pagecache.flush()).await
-        {
-            result
-        } else {
-            Err(Error::ReportableBug(
-                "threadpool failed to complete \
-                action before shutdown"
-            ))
+    // Looks up keys that must already be sorted, reusing the
+    // last node while the next key still falls within its bounds.
+    fn get_sorted_inner(
+        &self,
+        keys: Vec<IVec>,
+        guard: &Guard,
+    ) -> Result<Vec<(IVec, Option<IVec>)>> {
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_get);
+
+        let mut ret = Vec::with_capacity(keys.len());
+        let mut last_view: Option<View<'_>> = None;
+
+        for key in keys {
+            let covered = match &last_view {
+                Some(view) if &*key >= view.lo() => match view.hi() {
+                    Some(hi) => &*key < hi,
+                    None => true,
+                },
+                _ => false,
+            };
+
+            if !covered {
+                last_view = Some(self.view_for_key(&key, guard)?);
+            }
+
+            let view = last_view.as_ref().unwrap();
+            let value = view.node_kv_pair(&key).1.map(IVec::from);
+
+            ret.push((key, value));
         }
+
+        Ok(ret)
     }
 
-    /// Returns `true` if the `Tree` contains a value for
-    /// the specified key.
+    pub(crate) fn get_inner(
+        &self,
+        key: &[u8],
+        guard: &mut Guard,
+    ) -> Result<Conflictable<Option<IVec>>> {
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_get);
+
+        trace!("getting key {:?}", key);
+
+        let View { node_view, .. } = self.view_for_key(key.as_ref(), guard)?;
+
+        let pair = node_view.node_kv_pair(key.as_ref());
+        let val = pair.1.map(IVec::from);
+
+        Ok(Ok(val))
+    }
+
+    /// Delete a value, returning the old value if it existed.
     ///
     /// # Examples
     ///
@@ -962,54 +1499,1116 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// db.insert(&[0], vec![0])?;
-    /// assert!(db.contains_key(&[0])?);
-    /// assert!(!db.contains_key(&[1])?);
+    /// db.insert(&[1], vec![1]);
+    /// assert_eq!(db.remove(&[1]), Ok(Some(sled::IVec::from(vec![1]))));
+    /// assert_eq!(db.remove(&[1]), Ok(None));
     /// # Ok(()) }
     /// ```
-    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> Result<bool> {
-        self.get(key).map(|v| v.is_some())
+    #[doc(alias = "delete")]
+    #[doc(alias = "del")]
+    pub fn remove<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        let mut guard = pin();
+        let _cc = concurrency_control::read();
+        loop {
+            trace!("removing key {:?}", key.as_ref());
+
+            if let Ok(res) =
+                self.insert_inner(key.as_ref(), None, false, &mut guard)?
+            {
+                return Ok(res);
+            }
+        }
     }
 
-    /// Retrieve the key and value before the provided key,
-    /// if one exists.
+    /// Atomically remove a key and return the value it held,
+    /// if any. This is the same single operation as
+    /// [`Tree::remove`], and there is no need to call `get`
+    /// beforehand: doing so would let another thread change
+    /// the value between the two calls.
     ///
-    /// # Note
-    /// The order follows the Ord implementation for `Vec<u8>`:
+    /// # Examples
     ///
-    /// `[] < [0] < [255] < [255, 0] < [255, 255] ...`
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"job", b"payload")?;
+    /// assert_eq!(db.take(b"job")?, Some(sled::IVec::from(b"payload")));
+    /// assert_eq!(db.take(b"job")?, None);
+    /// # Ok(()) }
+    /// ```
+    #[doc(alias = "get_and_remove")]
+    pub fn take<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<IVec>> {
+        self.remove(key)
+    }
+
+    /// Compare and swap. Capable of unique creation, conditional modification,
+    /// or deletion. If old is `None`, this will only set the value if it
+    /// doesn't exist yet. If new is `None`, will delete the value if old is
+    /// correct. If both old and new are `Some`, will modify the value if
+    /// old is correct.
     ///
-    /// To retain the ordering of numerical types use big endian representation
+    /// It returns `Ok(Ok(()))` if operation finishes successfully.
+    ///
+    /// If it fails it returns:
+    ///     - `Ok(Err(CompareAndSwapError(current, proposed)))` if operation
+    ///       failed to setup a new value. `CompareAndSwapError` contains
+    ///       current and proposed values.
+    ///     - `Err(Error::Unsupported)` if the database is opened in read-only
+    ///       mode.
     ///
     /// # Examples
     ///
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use sled::IVec;
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// for i in 0..10 {
-    ///     db.insert(&[i], vec![i])
-    ///         .expect("should write successfully");
-    /// }
-    ///
-    /// assert_eq!(db.get_lt(&[]), Ok(None));
-    /// assert_eq!(db.get_lt(&[0]), Ok(None));
-    /// assert_eq!(
-    ///     db.get_lt(&[1]),
-    ///     Ok(Some((IVec::from(&[0]), IVec::from(&[0]))))
-    /// );
+    /// // unique creation
     /// assert_eq!(
-    ///     db.get_lt(&[9]),
-    ///     Ok(Some((IVec::from(&[8]), IVec::from(&[8]))))
+    ///     db.compare_and_swap(&[1], None as Option<&[u8]>, Some(&[10])),
+    ///     Ok(Ok(()))
     /// );
+    ///
+    /// // conditional modification
     /// assert_eq!(
-    ///     db.get_lt(&[10]),
-    ///     Ok(Some((IVec::from(&[9]), IVec::from(&[9]))))
+    ///     db.compare_and_swap(&[1], Some(&[10]), Some(&[20])),
+    ///     Ok(Ok(()))
     /// );
+    ///
+    /// // failed conditional modification -- the current value is returned in
+    /// // the error variant
+    /// let operation = db.compare_and_swap(&[1], Some(&[30]), Some(&[40]));
+    /// assert!(operation.is_ok()); // the operation succeeded
+    /// let modification = operation.unwrap();
+    /// assert!(modification.is_err());
+    /// let actual_value = modification.unwrap_err();
+    /// assert_eq!(actual_value.current.map(|ivec| ivec.to_vec()), Some(vec![20]));
+    ///
+    /// // conditional deletion
     /// assert_eq!(
-    ///     db.get_lt(&[255]),
-    ///     Ok(Some((IVec::from(&[9]), IVec::from(&[9]))))
+    ///     db.compare_and_swap(&[1], Some(&[20]), None as Option<&[u8]>),
+    ///     Ok(Ok(()))
+    /// );
+    /// assert_eq!(db.get(&[1]), Ok(None));
+    /// # Ok(()) }
+    /// ```
+    #[allow(clippy::needless_pass_by_value)]
+    #[doc(alias = "cas")]
+    #[doc(alias = "tas")]
+    #[doc(alias = "test_and_swap")]
+    #[doc(alias = "compare_and_set")]
+    pub fn compare_and_swap<K, OV, NV>(
+        &self,
+        key: K,
+        old: Option<OV>,
+        new: Option<NV>,
+    ) -> CompareAndSwapResult
+    where
+        K: AsRef<[u8]>,
+        OV: AsRef<[u8]>,
+        NV: Into<IVec>,
+    {
+        self.compare_and_swap_inner(
+            key.as_ref(),
+            old.as_ref().map(AsRef::as_ref),
+            new.map(Into::into),
+            true,
+        )
+    }
+
+    /// Compare and swap like [`Tree::compare_and_swap`], but
+    /// only report whether the swap happened, returning
+    /// `Ok(true)` if it did and `Ok(false)` if the current value
+    /// did not match `old`. Neither the current nor the proposed
+    /// value is copied when the swap fails, which avoids an
+    /// allocation for large values in retry loops that would
+    /// read the key again anyway.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"k", b"v1")?;
+    ///
+    /// assert!(db.compare_and_swap_unit(b"k", Some(b"v1"), Some(b"v2"))?);
+    /// assert!(!db.compare_and_swap_unit(b"k", Some(b"v1"), Some(b"v3"))?);
+    /// assert_eq!(db.get(b"k")?, Some(sled::IVec::from(b"v2")));
+    /// # Ok(()) }
+    /// ```
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn compare_and_swap_unit<K, OV, NV>(
+        &self,
+        key: K,
+        old: Option<OV>,
+        new: Option<NV>,
+    ) -> Result<bool>
+    where
+        K: AsRef<[u8]>,
+        OV: AsRef<[u8]>,
+        NV: Into<IVec>,
+    {
+        let swapped = self.compare_and_swap_inner(
+            key.as_ref(),
+            old.as_ref().map(AsRef::as_ref),
+            new.map(Into::into),
+            false,
+        )?;
+        Ok(swapped.is_ok())
+    }
+
+    /// Compare and swap like [`Tree::compare_and_swap`], but
+    /// report the result as a flat [`CasOutcome`] instead of a
+    /// nested `Result`, which makes the common `match` read
+    /// more naturally. `Err` is only returned for IO and other
+    /// database errors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use sled::CasOutcome;
+    ///
+    /// // unique creation
+    /// assert_eq!(
+    ///     db.cas(b"k", None as Option<&[u8]>, Some(b"v1"))?,
+    ///     CasOutcome::Applied { previous: None },
+    /// );
+    ///
+    /// // conditional modification
+    /// match db.cas(b"k", Some(b"v1"), Some(b"v2"))? {
+    ///     CasOutcome::Applied { previous } => {
+    ///         assert_eq!(previous, Some(sled::IVec::from(b"v1")))
+    ///     }
+    ///     CasOutcome::Mismatch { .. } => unreachable!(),
+    /// }
+    ///
+    /// // a stale expectation reports the current value
+    /// assert_eq!(
+    ///     db.cas(b"k", Some(b"v1"), None as Option<&[u8]>)?,
+    ///     CasOutcome::Mismatch { current: Some(sled::IVec::from(b"v2")) },
+    /// );
+    /// # Ok(()) }
+    /// ```
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn cas<K, OV, NV>(
+        &self,
+        key: K,
+        old: Option<OV>,
+        new: Option<NV>,
+    ) -> Result<CasOutcome>
+    where
+        K: AsRef<[u8]>,
+        OV: AsRef<[u8]>,
+        NV: Into<IVec>,
+    {
+        let old = old.as_ref().map(AsRef::as_ref);
+        match self.compare_and_swap_inner(
+            key.as_ref(),
+            old,
+            new.map(Into::into),
+            true,
+        )? {
+            // the swap only happens if the key held `old`
+            Ok(()) => Ok(CasOutcome::Applied { previous: old.map(IVec::from) }),
+            Err(CompareAndSwapError { current, .. }) => {
+                Ok(CasOutcome::Mismatch { current })
+            }
+        }
+    }
+
+    // The current value is only copied into a failed swap's
+    // `CompareAndSwapError` when `copy_current` is set, and is
+    // `None` otherwise.
+    fn compare_and_swap_inner(
+        &self,
+        key: &[u8],
+        old: Option<&[u8]>,
+        new2: Option<IVec>,
+        copy_current: bool,
+    ) -> CompareAndSwapResult {
+        trace!("cas'ing key {:?}", key);
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_cas);
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        // we need to retry caps until old != cur, since just because
+        // cap fails it doesn't mean our value was changed.
+        loop {
+            let View { pid, node_view, .. } = self.view_for_key(key, &guard)?;
+
+            let (encoded_key, current_value) = node_view.node_kv_pair(key);
+            let matches = match (old, &current_value) {
+                (None, None) => true,
+                (Some(o), Some(c)) => o == &**c,
+                _ => false,
+            };
+
+            if !matches {
+                return Ok(Err(CompareAndSwapError {
+                    current: current_value
+                        .filter(|_| copy_current)
+                        .map(IVec::from),
+                    proposed: new2,
+                }));
+            }
+
+            if current_value == new2.as_ref().map(AsRef::as_ref) {
+                // short-circuit no-op write. this is still correct
+                // because we verified that the input matches, so
+                // doing the work has the same semantic effect as not
+                // doing it in this case.
+                return Ok(Ok(()));
+            }
+
+            let mut subscriber_reservation = self.subscribers.reserve(key);
+
+            let frag = if let Some(ref new3) = new2 {
+                self.check_size_limits(key, Some(new3))?;
+                Link::Set(encoded_key, new3.clone())
+            } else {
+                Link::Del(encoded_key)
+            };
+            let link =
+                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+
+            if link.is_ok() {
+                self.context.pagecache.record_logical_write(
+                    key.len() + new2.as_ref().map_or(0, |v| v.len()),
+                );
+
+                if let Some(res) = subscriber_reservation.take() {
+                    let event = subscriber::Event::single_update(
+                        self.clone(),
+                        key.as_ref().into(),
+                        new2,
+                    );
+
+                    res.complete(&event);
+                }
+
+                return Ok(Ok(()));
+            }
+            #[cfg(feature = "metrics")]
+            M.tree_looped();
+        }
+    }
+
+    /// Append `suffix` to the value stored for `key`, but only if
+    /// the current value is exactly `expected_len` bytes long.
+    /// A missing key is treated as an empty value. Returns
+    /// `Ok(new_len)` if the append happened, or `Err(actual_len)`
+    /// if the value had a different length, which usually means
+    /// that another writer appended to it first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// assert_eq!(db.compare_and_append(b"log", 0, b"abc")?, Ok(3));
+    /// assert_eq!(db.compare_and_append(b"log", 3, b"de")?, Ok(5));
+    ///
+    /// // a stale length is rejected and the current one is reported
+    /// assert_eq!(db.compare_and_append(b"log", 3, b"xx")?, Err(5));
+    /// assert_eq!(db.get(b"log")?, Some(sled::IVec::from(b"abcde")));
+    /// # Ok(()) }
+    /// ```
+    pub fn compare_and_append<K, V>(
+        &self,
+        key: K,
+        expected_len: usize,
+        suffix: V,
+    ) -> Result<std::result::Result<usize, usize>>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        trace!("appending to key {:?}", key.as_ref());
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_cas);
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let suffix = suffix.as_ref();
+
+        loop {
+            let View { pid, node_view, .. } =
+                self.view_for_key(key.as_ref(), &guard)?;
+
+            let (encoded_key, current_value) =
+                node_view.node_kv_pair(key.as_ref());
+            let current_len = current_value.map_or(0, <[u8]>::len);
+
+            if current_len != expected_len {
+                return Ok(Err(current_len));
+            }
+
+            if suffix.is_empty() {
+                // nothing to append, and the length matched
+                return Ok(Ok(current_len));
+            }
+
+            let mut appended = Vec::with_capacity(current_len + suffix.len());
+            appended.extend_from_slice(current_value.unwrap_or(&[]));
+            appended.extend_from_slice(suffix);
+            let new_value = IVec::from(appended);
+            self.check_size_limits(key.as_ref(), Some(&new_value))?;
+
+            let mut subscriber_reservation = self.subscribers.reserve(&key);
+
+            let frag = Link::Set(encoded_key, new_value.clone());
+            let link =
+                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+
+            if link.is_ok() {
+                self.context
+                    .pagecache
+                    .record_logical_write(key.as_ref().len() + new_value.len());
+
+                let new_len = new_value.len();
+
+                if let Some(res) = subscriber_reservation.take() {
+                    let event = subscriber::Event::single_update(
+                        self.clone(),
+                        key.as_ref().into(),
+                        Some(new_value),
+                    );
+
+                    res.complete(&event);
+                }
+
+                return Ok(Ok(new_len));
+            }
+            #[cfg(feature = "metrics")]
+            M.tree_looped();
+        }
+    }
+
+    /// Returns the value stored for `key`, or if the key is not
+    /// present, calls `default`, inserts its result and returns
+    /// it. The check and the insert happen atomically, so a
+    /// concurrent writer can't slip in between them the way it
+    /// could with a `get` followed by an `insert`. `default` is
+    /// called at most once, even if the insert has to be retried
+    /// because of contention, and is not called at all if the
+    /// key is already present.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let v = db.get_or_insert_with(b"k", || b"computed".to_vec())?;
+    /// assert_eq!(v, sled::IVec::from(b"computed"));
+    ///
+    /// // the existing value is returned and the closure is not called
+    /// let v = db.get_or_insert_with(b"k", || -> Vec<u8> { unreachable!() })?;
+    /// assert_eq!(v, sled::IVec::from(b"computed"));
+    /// # Ok(()) }
+    /// ```
+    #[doc(alias = "compute_if_absent")]
+    pub fn get_or_insert_with<K, V, F>(
+        &self,
+        key: K,
+        default: F,
+    ) -> Result<IVec>
+    where
+        K: AsRef<[u8]>,
+        V: Into<IVec>,
+        F: FnOnce() -> V,
+    {
+        trace!("get_or_insert_with key {:?}", key.as_ref());
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_cas);
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let mut default = Some(default);
+        let mut new_value: Option<IVec> = None;
+
+        loop {
+            let View { pid, node_view, .. } =
+                self.view_for_key(key.as_ref(), &guard)?;
+
+            let (encoded_key, current_value) =
+                node_view.node_kv_pair(key.as_ref());
+
+            if let Some(current) = current_value {
+                return Ok(IVec::from(current));
+            }
+
+            if new_value.is_none() {
+                let value = (default.take().unwrap())().into();
+                self.check_size_limits(key.as_ref(), Some(&value))?;
+                new_value = Some(value);
+            }
+            let value = new_value.clone().unwrap();
+
+            let mut subscriber_reservation = self.subscribers.reserve(&key);
+
+            let frag = Link::Set(encoded_key, value.clone());
+            let link =
+                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+
+            if link.is_ok() {
+                self.context
+                    .pagecache
+                    .record_logical_write(key.as_ref().len() + value.len());
+
+                if let Some(res) = subscriber_reservation.take() {
+                    let event = subscriber::Event::single_update(
+                        self.clone(),
+                        key.as_ref().into(),
+                        Some(value.clone()),
+                    );
+
+                    res.complete(&event);
+                }
+
+                return Ok(value);
+            }
+            #[cfg(feature = "metrics")]
+            M.tree_looped();
+        }
+    }
+
+    /// Treats the value stored for `key` as a big-endian `u64`
+    /// and atomically adds `delta` to it, wrapping around on
+    /// overflow, and returns the previous value. A missing key
+    /// is treated as 0, so it is created with the value `delta`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Error::Unsupported` if the current value is not
+    /// exactly 8 bytes long, in which case it is left untouched.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// assert_eq!(db.fetch_add(b"counter", 5)?, 0);
+    /// assert_eq!(db.fetch_add(b"counter", 2)?, 5);
+    /// assert_eq!(db.fetch_sub(b"counter", 3)?, 7);
+    /// assert_eq!(db.get(b"counter")?.unwrap(), 4_u64.to_be_bytes());
+    ///
+    /// db.insert(b"name", b"sled")?;
+    /// assert!(db.fetch_add(b"name", 1).is_err());
+    /// # Ok(()) }
+    /// ```
+    pub fn fetch_add<K: AsRef<[u8]>>(&self, key: K, delta: u64) -> Result<u64> {
+        self.fetch_update_u64(key.as_ref(), |current| {
+            current.wrapping_add(delta)
+        })
+    }
+
+    /// Like [`Tree::fetch_add`], but subtracts `delta` from the
+    /// stored value, wrapping around on underflow, and returns
+    /// the previous value.
+    pub fn fetch_sub<K: AsRef<[u8]>>(&self, key: K, delta: u64) -> Result<u64> {
+        self.fetch_update_u64(key.as_ref(), |current| {
+            current.wrapping_sub(delta)
+        })
+    }
+
+    /// Like [`Tree::fetch_add`], but stores the maximum of the
+    /// stored value and `value`, and returns the previous value.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// assert_eq!(db.fetch_max(b"high", 10)?, 0);
+    /// assert_eq!(db.fetch_max(b"high", 3)?, 10);
+    /// assert_eq!(db.fetch_min(b"high", 3)?, 10);
+    /// assert_eq!(db.get(b"high")?.unwrap(), 3_u64.to_be_bytes());
+    /// # Ok(()) }
+    /// ```
+    pub fn fetch_max<K: AsRef<[u8]>>(&self, key: K, value: u64) -> Result<u64> {
+        self.fetch_update_u64(key.as_ref(), |current| current.max(value))
+    }
+
+    /// Like [`Tree::fetch_add`], but stores the minimum of the
+    /// stored value and `value`, and returns the previous value.
+    /// A missing key is treated as 0, so it is created as 0.
+    pub fn fetch_min<K: AsRef<[u8]>>(&self, key: K, value: u64) -> Result<u64> {
+        self.fetch_update_u64(key.as_ref(), |current| current.min(value))
+    }
+
+    fn fetch_update_u64<F>(&self, key: &[u8], f: F) -> Result<u64>
+    where
+        F: Fn(u64) -> u64,
+    {
+        trace!("updating numeric value of key {:?}", key);
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_cas);
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        loop {
+            let View { pid, node_view, .. } = self.view_for_key(key, &guard)?;
+
+            let (encoded_key, current_value) = node_view.node_kv_pair(key);
+            let current = match current_value {
+                None => 0,
+                Some(bytes) if bytes.len() == 8 => {
+                    let mut buf = [0; 8];
+                    buf.copy_from_slice(bytes);
+                    u64::from_be_bytes(buf)
+                }
+                Some(_) => {
+                    return Err(Error::Unsupported(
+                        "numeric updates require values of exactly 8 bytes",
+                    ))
+                }
+            };
+
+            let new = f(current);
+            if current_value.is_some() && new == current {
+                return Ok(current);
+            }
+
+            let new_value = IVec::from(&new.to_be_bytes());
+            self.check_size_limits(key, Some(&new_value))?;
+
+            let mut subscriber_reservation = self.subscribers.reserve(key);
+
+            let frag = Link::Set(encoded_key, new_value.clone());
+            let link =
+                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+
+            if link.is_ok() {
+                self.context
+                    .pagecache
+                    .record_logical_write(key.len() + new_value.len());
+
+                if let Some(res) = subscriber_reservation.take() {
+                    let event = subscriber::Event::single_update(
+                        self.clone(),
+                        key.into(),
+                        Some(new_value),
+                    );
+
+                    res.complete(&event);
+                }
+
+                return Ok(current);
+            }
+            #[cfg(feature = "metrics")]
+            M.tree_looped();
+        }
+    }
+
+    /// Fetch the value, apply a function to it and return the result.
+    ///
+    /// # Note
+    ///
+    /// This may call the function multiple times if the value has been
+    /// changed from other threads in the meantime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use sled::{Config, Error, IVec};
+    /// use std::convert::TryInto;
+    ///
+    /// let config = Config::new().temporary(true);
+    /// let db = config.open()?;
+    ///
+    /// fn u64_to_ivec(number: u64) -> IVec {
+    ///     IVec::from(number.to_be_bytes().to_vec())
+    /// }
+    ///
+    /// let zero = u64_to_ivec(0);
+    /// let one = u64_to_ivec(1);
+    /// let two = u64_to_ivec(2);
+    /// let three = u64_to_ivec(3);
+    ///
+    /// fn increment(old: Option<&[u8]>) -> Option<Vec<u8>> {
+    ///     let number = match old {
+    ///         Some(bytes) => {
+    ///             let array: [u8; 8] = bytes.try_into().unwrap();
+    ///             let number = u64::from_be_bytes(array);
+    ///             number + 1
+    ///         }
+    ///         None => 0,
+    ///     };
+    ///
+    ///     Some(number.to_be_bytes().to_vec())
+    /// }
+    ///
+    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(zero)));
+    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(one)));
+    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(two)));
+    /// assert_eq!(db.update_and_fetch("counter", increment), Ok(Some(three)));
+    /// # Ok(()) }
+    /// ```
+    pub fn update_and_fetch<K, V, F>(
+        &self,
+        key: K,
+        mut f: F,
+    ) -> Result<Option<IVec>>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(Option<&[u8]>) -> Option<V>,
+        V: Into<IVec>,
+    {
+        let key_ref = key.as_ref();
+        let mut current = self.get(key_ref)?;
+
+        loop {
+            let tmp = current.as_ref().map(AsRef::as_ref);
+            let next = f(tmp).map(Into::into);
+            match self.compare_and_swap::<_, _, IVec>(
+                key_ref,
+                tmp,
+                next.clone(),
+            )? {
+                Ok(()) => return Ok(next),
+                Err(CompareAndSwapError { current: cur, .. }) => {
+                    current = cur;
+                }
+            }
+        }
+    }
+
+    /// Fetch the value, apply a function to it and return the previous value.
+    ///
+    /// # Note
+    ///
+    /// This may call the function multiple times if the value has been
+    /// changed from other threads in the meantime.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use sled::{Config, Error, IVec};
+    /// use std::convert::TryInto;
+    ///
+    /// let config = Config::new().temporary(true);
+    /// let db = config.open()?;
+    ///
+    /// fn u64_to_ivec(number: u64) -> IVec {
+    ///     IVec::from(number.to_be_bytes().to_vec())
+    /// }
+    ///
+    /// let zero = u64_to_ivec(0);
+    /// let one = u64_to_ivec(1);
+    /// let two = u64_to_ivec(2);
+    ///
+    /// fn increment(old: Option<&[u8]>) -> Option<Vec<u8>> {
+    ///     let number = match old {
+    ///         Some(bytes) => {
+    ///             let array: [u8; 8] = bytes.try_into().unwrap();
+    ///             let number = u64::from_be_bytes(array);
+    ///             number + 1
+    ///         }
+    ///         None => 0,
+    ///     };
+    ///
+    ///     Some(number.to_be_bytes().to_vec())
+    /// }
+    ///
+    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(None));
+    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(Some(zero)));
+    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(Some(one)));
+    /// assert_eq!(db.fetch_and_update("counter", increment), Ok(Some(two)));
+    /// # Ok(()) }
+    /// ```
+    pub fn fetch_and_update<K, V, F>(
+        &self,
+        key: K,
+        mut f: F,
+    ) -> Result<Option<IVec>>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(Option<&[u8]>) -> Option<V>,
+        V: Into<IVec>,
+    {
+        let key_ref = key.as_ref();
+        let mut current = self.get(key_ref)?;
+
+        loop {
+            let tmp = current.as_ref().map(AsRef::as_ref);
+            let next = f(tmp);
+            match self.compare_and_swap(key_ref, tmp, next)? {
+                Ok(()) => return Ok(current),
+                Err(CompareAndSwapError { current: cur, .. }) => {
+                    current = cur;
+                }
+            }
+        }
+    }
+
+    /// Subscribe to `Event`s that happen to keys that have
+    /// the specified prefix. Events for particular keys are
+    /// guaranteed to be witnessed in the same order by all
+    /// threads, but threads may witness different interleavings
+    /// of `Event`s across different keys. If subscribers don't
+    /// keep up with new writes, they will cause new writes
+    /// to block. There is a buffer of 1024 items per
+    /// `Subscriber`. This can be used to build reactive
+    /// and replicated systems.
+    ///
+    /// `Subscriber` implements both `Iterator<Item = Event>`
+    /// and `Future<Output=Option<Event>>`
+    ///
+    /// # Examples
+    ///
+    /// Synchronous, blocking subscriber:
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// // watch all events by subscribing to the empty prefix
+    /// let mut subscriber = db.watch_prefix(vec![]);
+    ///
+    /// let tree_2 = db.clone();
+    /// let thread = std::thread::spawn(move || {
+    ///     db.insert(vec![0], vec![1])
+    /// });
+    ///
+    /// // `Subscription` implements `Iterator<Item=Event>`
+    /// for event in subscriber.take(1) {
+    ///     // Events occur due to single key operations,
+    ///     // batches, or transactions. The tree is included
+    ///     // so that you may perform a new transaction or
+    ///     // operation in response to the event.
+    ///     for (tree, key, value_opt) in &event {
+    ///         if let Some(value) = value_opt {
+    ///             // key `key` was set to value `value`
+    ///         } else {
+    ///             // key `key` was removed
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// # thread.join().unwrap();
+    /// # Ok(()) }
+    /// ```
+    /// Asynchronous, non-blocking subscriber:
+    ///
+    /// `Subscription` implements `Future<Output=Option<Event>>`.
+    ///
+    /// ```
+    /// # async fn foo() {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open().unwrap();
+    /// # let mut subscriber = db.watch_prefix(vec![]);
+    /// while let Some(event) = (&mut subscriber).await {
+    ///     /* use it */
+    /// }
+    /// # }
+    /// ```
+    pub fn watch_prefix<P: AsRef<[u8]>>(&self, prefix: P) -> Subscriber {
+        self.subscribers.register(prefix.as_ref())
+    }
+
+    /// Subscribe to `Event`s that happen to keys for which
+    /// `filter` returns `true`, for watching keys that don't
+    /// share a prefix. Delivery works exactly like
+    /// [`Tree::watch_prefix`], and an `Event` for a batch or
+    /// transaction is delivered if any of its keys match.
+    ///
+    /// The filter is called for every key written to this
+    /// `Tree` while the subscriber is alive, on the writing
+    /// thread and before the write is applied, so it should
+    /// be cheap and must not call back into the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let mut subscriber = db.watch(|key| key.ends_with(b"!"));
+    ///
+    /// db.insert(b"quiet", b"1")?;
+    /// db.insert(b"loud!", b"2")?;
+    ///
+    /// let event = subscriber.next().unwrap();
+    /// let (_tree, key, _value) = event.iter().next().unwrap();
+    /// assert_eq!(key, b"loud!");
+    /// # Ok(()) }
+    /// ```
+    pub fn watch<F>(&self, filter: F) -> Subscriber
+    where
+        F: Fn(&[u8]) -> bool + Send + Sync + 'static,
+    {
+        self.subscribers.register_filter(filter)
+    }
+
+    /// Synchronously flushes all dirty IO buffers and calls
+    /// fsync. If this succeeds, it is guaranteed that all
+    /// previous writes will be recovered if the system
+    /// crashes. Returns the number of bytes flushed during
+    /// this call.
+    ///
+    /// Flushing can take quite a lot of time, and you should
+    /// measure the performance impact of using it on
+    /// realistic sustained workloads running on realistic
+    /// hardware.
+    ///
+    /// This is called automatically on drop.
+    pub fn flush(&self) -> Result<usize> {
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_flush);
+
+        self.context.pagecache.flush()
+    }
+
+    /// Synchronously flushes all dirty IO buffers, like
+    /// [`Tree::flush`], and returns a [`Checkpoint`] that
+    /// marks the point in the log that is now durable.
+    /// The checkpoint can be persisted outside of sled and
+    /// later passed to [`Tree::durable_at_least`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", vec![1])?;
+    /// let checkpoint = db.checkpoint()?;
+    /// assert!(db.durable_at_least(&checkpoint));
+    ///
+    /// let restored = sled::Checkpoint::from_bytes(checkpoint.to_bytes());
+    /// assert_eq!(restored, checkpoint);
+    /// # Ok(()) }
+    /// ```
+    #[doc(alias = "flush_and_return_epoch")]
+    pub fn checkpoint(&self) -> Result<Checkpoint> {
+        Ok(self.flush_report()?.durable_through)
+    }
+
+    /// Synchronously flushes all dirty IO buffers, like
+    /// [`Tree::flush`], and returns both the number of bytes
+    /// written during this call and the [`Checkpoint`] that
+    /// is now durable. Every write that completed before this
+    /// call is covered by `durable_through`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", vec![1])?;
+    /// let first = db.flush_report()?;
+    /// assert!(first.bytes > 0);
+    ///
+    /// // nothing new to write
+    /// let second = db.flush_report()?;
+    /// assert_eq!(second.bytes, 0);
+    /// assert!(second.durable_through >= first.durable_through);
+    /// # Ok(()) }
+    /// ```
+    pub fn flush_report(&self) -> Result<FlushResult> {
+        let bytes = self.context.pagecache.flush()?;
+        let lsn = self.context.pagecache.log.stable_offset();
+        Ok(FlushResult { bytes, durable_through: Checkpoint { lsn } })
+    }
+
+    /// Returns `true` if everything that was durable when
+    /// the given [`Checkpoint`] was taken is still durable,
+    /// meaning the database is at or beyond that checkpoint.
+    /// This returns `false` if, for instance, the database was
+    /// recovered from a log that was truncated before it.
+    pub fn durable_at_least(&self, checkpoint: &Checkpoint) -> bool {
+        self.context.pagecache.log.stable_offset() >= checkpoint.lsn
+    }
+
+    /// Returns the [`Checkpoint`] that is currently durable
+    /// without flushing anything. Writes that completed after
+    /// the returned checkpoint was taken are not covered by it
+    /// until a later flush, whether explicit or performed by
+    /// the background flusher, moves the durable point past
+    /// them. This is mostly useful for crash tests that need
+    /// to reason about which writes must survive a restart.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// let before = db.durable_checkpoint();
+    /// db.insert(b"a", vec![1])?;
+    /// let flushed = db.checkpoint()?;
+    ///
+    /// assert!(flushed > before);
+    /// assert!(db.durable_checkpoint() >= flushed);
+    /// # Ok(()) }
+    /// ```
+    #[doc(alias = "current_flush_epoch")]
+    pub fn durable_checkpoint(&self) -> Checkpoint {
+        Checkpoint { lsn: self.context.pagecache.log.stable_offset() }
+    }
+
+    /// Asynchronously flushes all dirty IO buffers
+    /// and calls fsync. If this succeeds, it is
+    /// guaranteed that all previous writes will
+    /// be recovered if the system crashes. Returns
+    /// the number of bytes flushed during this call.
+    ///
+    /// Flushing can take quite a lot of time, and you
+    /// should measure the performance impact of
+    /// using it on realistic sustained workloads
+    /// running on realistic hardware.
+    // this clippy check is miss-firing on async code.
+    #[allow(clippy::used_underscore_binding)]
+    #[allow(clippy::shadow_same)]
+    pub async fn flush_async(&self) -> Result<usize> {
+        if let Some(result) = self.flush_in_background().promise.await {
+            result
+        } else {
+            Err(Error::ReportableBug(
+                "threadpool failed to complete \
+                action before shutdown"
+            ))
+        }
+    }
+
+    /// Flushes all dirty IO buffers and calls fsync like
+    /// [`Tree::flush`], but gives up waiting after `timeout`.
+    /// Returns the number of bytes flushed, or `None` if the
+    /// flush did not complete in time, for example because the
+    /// disk is hung. This is meant for shutting down within a
+    /// deadline, where losing the most recent writes is better
+    /// than blocking past it.
+    ///
+    /// The flush runs on a background thread. If it times out,
+    /// it keeps running and may still complete later, but
+    /// nothing written since the last successful flush is
+    /// guaranteed to be durable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use std::time::Duration;
+    ///
+    /// db.insert(b"k", b"v")?;
+    /// if db.flush_timeout(Duration::from_secs(10))?.is_none() {
+    ///     eprintln!("gave up on flushing");
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn flush_timeout(&self, timeout: Duration) -> Result<Option<usize>> {
+        let pagecache = self.context.pagecache.clone();
+        let mut flush = threadpool::spawn(move || pagecache.flush());
+        match flush.wait_timeout(timeout) {
+            Ok(result) => result.map(Some),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::ReportableBug(
+                "threadpool failed to complete action before shutdown",
+            )),
+        }
+    }
+
+    /// Starts flushing every write that completed before this
+    /// call, like [`Tree::flush`], without waiting for it. The
+    /// IO buffers are written out and fsynced on a background
+    /// thread, the same way [`Tree::flush_async`] does it.
+    /// Writes that happen after this call may or may not be
+    /// covered by the returned [`FlushHandle`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"k", b"v")?;
+    /// let flush = db.flush_in_background();
+    ///
+    /// // keep working while the flush proceeds
+    /// db.insert(b"other", b"v")?;
+    ///
+    /// let _bytes = flush.wait()?;
+    /// # Ok(()) }
+    /// ```
+    pub fn flush_in_background(&self) -> FlushHandle {
+        let pagecache = self.context.pagecache.clone();
+        FlushHandle { promise: threadpool::spawn(move || pagecache.flush()) }
+    }
+
+    /// Returns `true` if the `Tree` contains a value for
+    /// the specified key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(&[0], vec![0])?;
+    /// assert!(db.contains_key(&[0])?);
+    /// assert!(!db.contains_key(&[1])?);
+    /// # Ok(()) }
+    /// ```
+    pub fn contains_key<K: AsRef<[u8]>>(&self, key: K) -> Result<bool> {
+        self.get(key).map(|v| v.is_some())
+    }
+
+    /// Retrieve the key and value before the provided key,
+    /// if one exists.
+    ///
+    /// # Note
+    /// The order follows the Ord implementation for `Vec<u8>`:
+    ///
+    /// `[] < [0] < [255] < [255, 0] < [255, 255] ...`
+    ///
+    /// To retain the ordering of numerical types use big endian representation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use sled::IVec;
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..10 {
+    ///     db.insert(&[i], vec![i])
+    ///         .expect("should write successfully");
+    /// }
+    ///
+    /// assert_eq!(db.get_lt(&[]), Ok(None));
+    /// assert_eq!(db.get_lt(&[0]), Ok(None));
+    /// assert_eq!(
+    ///     db.get_lt(&[1]),
+    ///     Ok(Some((IVec::from(&[0]), IVec::from(&[0]))))
+    /// );
+    /// assert_eq!(
+    ///     db.get_lt(&[9]),
+    ///     Ok(Some((IVec::from(&[8]), IVec::from(&[8]))))
+    /// );
+    /// assert_eq!(
+    ///     db.get_lt(&[10]),
+    ///     Ok(Some((IVec::from(&[9]), IVec::from(&[9]))))
+    /// );
+    /// assert_eq!(
+    ///     db.get_lt(&[255]),
+    ///     Ok(Some((IVec::from(&[9]), IVec::from(&[9]))))
     /// );
     /// # Ok(()) }
     /// ```
@@ -1018,79 +2617,472 @@ impl Tree {
         K: AsRef<[u8]>,
     {
         #[cfg(feature = "metrics")]
-        let _measure = Measure::new(&M.tree_get);
-        self.range(..key).next_back().transpose()
+        let _measure = Measure::new(&M.tree_get);
+        self.range(..key).next_back().transpose()
+    }
+
+    /// Retrieve the next key and value from the `Tree` after the
+    /// provided key.
+    ///
+    /// # Note
+    /// The order follows the Ord implementation for `Vec<u8>`:
+    ///
+    /// `[] < [0] < [255] < [255, 0] < [255, 255] ...`
+    ///
+    /// To retain the ordering of numerical types use big endian representation
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// use sled::IVec;
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..10 {
+    ///     db.insert(&[i], vec![i])?;
+    /// }
+    ///
+    /// assert_eq!(
+    ///     db.get_gt(&[]),
+    ///     Ok(Some((IVec::from(&[0]), IVec::from(&[0]))))
+    /// );
+    /// assert_eq!(
+    ///     db.get_gt(&[0]),
+    ///     Ok(Some((IVec::from(&[1]), IVec::from(&[1]))))
+    /// );
+    /// assert_eq!(
+    ///     db.get_gt(&[1]),
+    ///     Ok(Some((IVec::from(&[2]), IVec::from(&[2]))))
+    /// );
+    /// assert_eq!(
+    ///     db.get_gt(&[8]),
+    ///     Ok(Some((IVec::from(&[9]), IVec::from(&[9]))))
+    /// );
+    /// assert_eq!(db.get_gt(&[9]), Ok(None));
+    ///
+    /// db.insert(500u16.to_be_bytes(), vec![10]);
+    /// assert_eq!(
+    ///     db.get_gt(&499u16.to_be_bytes()),
+    ///     Ok(Some((IVec::from(&500u16.to_be_bytes()), IVec::from(&[10]))))
+    /// );
+    /// # Ok(()) }
+    /// ```
+    pub fn get_gt<K>(&self, key: K) -> Result<Option<(IVec, IVec)>>
+    where
+        K: AsRef<[u8]>,
+    {
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_get);
+        self.range((ops::Bound::Excluded(key), ops::Bound::Unbounded))
+            .next()
+            .transpose()
+    }
+
+    /// Merge state directly into a given key's value using the
+    /// configured merge operator. This allows state to be written
+    /// into a value directly, without any read-modify-write steps.
+    /// Merge operators can be used to implement arbitrary data
+    /// structures.
+    ///
+    /// Calling `merge` will return an `Unsupported` error if it
+    /// is called without first setting a merge operator function.
+    ///
+    /// Merge operators are shared by all instances of a particular
+    /// `Tree`. Different merge operators may be set on different
+    /// `Tree`s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use sled::IVec;
+    ///
+    /// fn concatenate_merge(
+    ///   _key: &[u8],               // the key being merged
+    ///   old_value: Option<&[u8]>,  // the previous value, if one existed
+    ///   merged_bytes: &[u8]        // the new bytes being merged in
+    /// ) -> Option<Vec<u8>> {       // set the new value, return None to delete
+    ///   let mut ret = old_value
+    ///     .map(|ov| ov.to_vec())
+    ///     .unwrap_or_else(|| vec![]);
+    ///
+    ///   ret.extend_from_slice(merged_bytes);
+    ///
+    ///   Some(ret)
+    /// }
+    ///
+    /// db.set_merge_operator(concatenate_merge);
+    ///
+    /// let k = b"k1";
+    ///
+    /// db.insert(k, vec![0]);
+    /// db.merge(k, vec![1]);
+    /// db.merge(k, vec![2]);
+    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![0, 1, 2]))));
+    ///
+    /// // Replace previously merged data. The merge function will not be called.
+    /// db.insert(k, vec![3]);
+    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![3]))));
+    ///
+    /// // Merges on non-present values will cause the merge function to be called
+    /// // with `old_value == None`. If the merge function returns something (which it
+    /// // does, in this case) a new value will be inserted.
+    /// db.remove(k);
+    /// db.merge(k, vec![4]);
+    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![4]))));
+    /// # Ok(()) }
+    /// ```
+    pub fn merge<K, V>(&self, key: K, value: V) -> Result<Option<IVec>>
+    where
+        K: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        let _cc = concurrency_control::read();
+        loop {
+            if let Ok(merge) = self.merge_inner(key.as_ref(), value.as_ref())? {
+                return Ok(merge);
+            }
+        }
+    }
+
+    pub(crate) fn merge_inner(
+        &self,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<Conflictable<Option<IVec>>> {
+        trace!("merging key {:?}", key);
+        #[cfg(feature = "metrics")]
+        let _measure = Measure::new(&M.tree_merge);
+
+        let merge_operator_opt = self.merge_operator.read();
+
+        if merge_operator_opt.is_none() {
+            return Err(Error::Unsupported(
+                "must set a merge operator on this Tree \
+                 before calling merge by calling \
+                 Tree::set_merge_operator"
+            ));
+        }
+
+        let merge_operator = merge_operator_opt.as_ref().unwrap();
+
+        loop {
+            let guard = pin();
+            let View { pid, node_view, .. } =
+                self.view_for_key(key.as_ref(), &guard)?;
+
+            let (encoded_key, current_value) =
+                node_view.node_kv_pair(key.as_ref());
+            let tmp = current_value.as_ref().map(AsRef::as_ref);
+            let new_opt = merge_operator(key, tmp, value).map(IVec::from);
+
+            if new_opt.as_ref().map(AsRef::as_ref) == current_value {
+                // short-circuit no-op write
+                return Ok(Ok(new_opt));
+            }
+
+            let mut subscriber_reservation = self.subscribers.reserve(key);
+
+            let frag = if let Some(ref new) = new_opt {
+                self.check_size_limits(key, Some(new))?;
+                Link::Set(encoded_key, new.clone())
+            } else {
+                Link::Del(encoded_key)
+            };
+            let link =
+                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+
+            if link.is_ok() {
+                self.context.pagecache.record_logical_write(
+                    key.len() + new_opt.as_ref().map_or(0, |v| v.len()),
+                );
+
+                if let Some(res) = subscriber_reservation.take() {
+                    let event = subscriber::Event::single_update(
+                        self.clone(),
+                        key.as_ref().into(),
+                        new_opt.clone(),
+                    );
+
+                    res.complete(&event);
+                }
+
+                return Ok(Ok(new_opt));
+            }
+            #[cfg(feature = "metrics")]
+            M.tree_looped();
+        }
     }
 
-    /// Retrieve the next key and value from the `Tree` after the
-    /// provided key.
+    /// Sets a merge operator for use with the `merge` function.
     ///
-    /// # Note
-    /// The order follows the Ord implementation for `Vec<u8>`:
+    /// Merge state directly into a given key's value using the
+    /// configured merge operator. This allows state to be written
+    /// into a value directly, without any read-modify-write steps.
+    /// Merge operators can be used to implement arbitrary data
+    /// structures.
     ///
-    /// `[] < [0] < [255] < [255, 0] < [255, 255] ...`
+    /// A merge operator that returns `None` removes the key.
+    ///
+    /// # Errors
+    ///
+    /// Calling `merge` will return an `Unsupported` error if no
+    /// merge operator has been configured.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use sled::IVec;
+    ///
+    /// fn concatenate_merge(
+    ///   _key: &[u8],               // the key being merged
+    ///   old_value: Option<&[u8]>,  // the previous value, if one existed
+    ///   merged_bytes: &[u8]        // the new bytes being merged in
+    /// ) -> Option<Vec<u8>> {       // set the new value, return None to delete
+    ///   let mut ret = old_value
+    ///     .map(|ov| ov.to_vec())
+    ///     .unwrap_or_else(|| vec![]);
+    ///
+    ///   ret.extend_from_slice(merged_bytes);
+    ///
+    ///   Some(ret)
+    /// }
+    ///
+    /// db.set_merge_operator(concatenate_merge);
+    ///
+    /// let k = b"k1";
+    ///
+    /// db.insert(k, vec![0]);
+    /// db.merge(k, vec![1]);
+    /// db.merge(k, vec![2]);
+    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![0, 1, 2]))));
+    ///
+    /// // Replace previously merged data. The merge function will not be called.
+    /// db.insert(k, vec![3]);
+    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![3]))));
+    ///
+    /// // Merges on non-present values will cause the merge function to be called
+    /// // with `old_value == None`. If the merge function returns something (which it
+    /// // does, in this case) a new value will be inserted.
+    /// db.remove(k);
+    /// db.merge(k, vec![4]);
+    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![4]))));
+    /// # Ok(()) }
+    /// ```
+    pub fn set_merge_operator(
+        &self,
+        merge_operator: impl MergeOperator + 'static,
+    ) {
+        let mut mo_write = self.merge_operator.write();
+        *mo_write = Some(Box::new(merge_operator));
+    }
+
+    /// Create a double-ended iterator over the tuples of keys and
+    /// values in this tree.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use sled::IVec;
+    /// db.insert(&[1], vec![10]);
+    /// db.insert(&[2], vec![20]);
+    /// db.insert(&[3], vec![30]);
+    /// let mut iter = db.iter();
+    /// assert_eq!(
+    ///     iter.next().unwrap(),
+    ///     Ok((IVec::from(&[1]), IVec::from(&[10])))
+    /// );
+    /// assert_eq!(
+    ///     iter.next().unwrap(),
+    ///     Ok((IVec::from(&[2]), IVec::from(&[20])))
+    /// );
+    /// assert_eq!(
+    ///     iter.next().unwrap(),
+    ///     Ok((IVec::from(&[3]), IVec::from(&[30])))
+    /// );
+    /// assert_eq!(iter.next(), None);
+    /// # Ok(()) }
+    /// ```
+    pub fn iter(&self) -> Iter {
+        self.range::<Vec<u8>, _>(..)
+    }
+
+    /// Create a double-ended iterator over all keys starting at
+    /// `key`, including `key` itself if `inclusive` is `true`.
+    /// This is shorthand for `range((Excluded(key), Unbounded))`
+    /// or `range(key..)`, and is useful for resuming a scan after
+    /// the last key that was seen.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..4_u8 {
+    ///     db.insert(&[i], vec![i])?;
+    /// }
+    ///
+    /// let keys: Vec<sled::IVec> =
+    ///     db.iter_from(&[1], false).keys().collect::<Result<_, _>>()?;
+    /// assert_eq!(keys, vec![sled::IVec::from(&[2]), sled::IVec::from(&[3])]);
+    ///
+    /// let last = db.iter_from(&[1], true).next_back().unwrap()?;
+    /// assert_eq!(last.0, sled::IVec::from(&[3]));
+    /// # Ok(()) }
+    /// ```
+    pub fn iter_from<K: AsRef<[u8]>>(&self, key: K, inclusive: bool) -> Iter {
+        let lo = if inclusive {
+            ops::Bound::Included(key.as_ref())
+        } else {
+            ops::Bound::Excluded(key.as_ref())
+        };
+        self.range::<&[u8], _>((lo, ops::Bound::Unbounded))
+    }
+
+    /// Create a double-ended iterator over tuples of keys and values,
+    /// where the keys fall within the specified range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use sled::IVec;
+    /// db.insert(&[0], vec![0])?;
+    /// db.insert(&[1], vec![10])?;
+    /// db.insert(&[2], vec![20])?;
+    /// db.insert(&[3], vec![30])?;
+    /// db.insert(&[4], vec![40])?;
+    /// db.insert(&[5], vec![50])?;
+    ///
+    /// let start: &[u8] = &[2];
+    /// let end: &[u8] = &[4];
+    /// let mut r = db.range(start..end);
+    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[2]), IVec::from(&[20]))));
+    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[3]), IVec::from(&[30]))));
+    /// assert_eq!(r.next(), None);
+    ///
+    /// let mut r = db.range(start..end).rev();
+    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[3]), IVec::from(&[30]))));
+    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[2]), IVec::from(&[20]))));
+    /// assert_eq!(r.next(), None);
+    /// # Ok(()) }
+    /// ```
+    pub fn range<K, R>(&self, range: R) -> Iter
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let lo = match range.start_bound() {
+            ops::Bound::Included(start) => {
+                ops::Bound::Included(IVec::from(start.as_ref()))
+            }
+            ops::Bound::Excluded(start) => {
+                ops::Bound::Excluded(IVec::from(start.as_ref()))
+            }
+            ops::Bound::Unbounded => ops::Bound::Included(IVec::from(&[])),
+        };
+
+        let hi = match range.end_bound() {
+            ops::Bound::Included(end) => {
+                ops::Bound::Included(IVec::from(end.as_ref()))
+            }
+            ops::Bound::Excluded(end) => {
+                ops::Bound::Excluded(IVec::from(end.as_ref()))
+            }
+            ops::Bound::Unbounded => ops::Bound::Unbounded,
+        };
+
+        Iter {
+            tree: self.clone(),
+            hi,
+            lo,
+            cached_node: None,
+            going_forward: true,
+            cold: false,
+            keys_only: false,
+        }
+    }
+
+    /// Returns up to `n` items starting at a [`Cursor`] that
+    /// was returned by [`Iter::take_cursor`] or by a previous
+    /// call to this method, along with a cursor for the page
+    /// after that, or `None` if the range has been exhausted.
+    pub fn range_from_cursor(
+        &self,
+        cursor: &Cursor,
+        n: usize,
+    ) -> iter::CursorPage {
+        let lo = ops::Bound::Included(cursor.next.clone());
+        self.range((lo, cursor.hi.clone())).take_cursor(n)
+    }
+
+    /// Fold over the keys and values in a range of the `Tree`,
+    /// passing the accumulator along with borrowed key and value
+    /// slices to `f`, and returning the final accumulator. The
+    /// first error encountered while reading the range ends the
+    /// fold and is returned.
     ///
-    /// To retain the ordering of numerical types use big endian representation
+    /// This walks the range with [`Tree::for_each_in_range`], so
+    /// keys and values are read in place instead of being copied
+    /// into `IVec`s, and the same consistency caveats apply.
     ///
     /// # Examples
     ///
     /// ```
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
-    /// use sled::IVec;
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// for i in 0..10 {
+    /// for i in 0..10_u8 {
     ///     db.insert(&[i], vec![i])?;
     /// }
     ///
-    /// assert_eq!(
-    ///     db.get_gt(&[]),
-    ///     Ok(Some((IVec::from(&[0]), IVec::from(&[0]))))
-    /// );
-    /// assert_eq!(
-    ///     db.get_gt(&[0]),
-    ///     Ok(Some((IVec::from(&[1]), IVec::from(&[1]))))
-    /// );
-    /// assert_eq!(
-    ///     db.get_gt(&[1]),
-    ///     Ok(Some((IVec::from(&[2]), IVec::from(&[2]))))
-    /// );
-    /// assert_eq!(
-    ///     db.get_gt(&[8]),
-    ///     Ok(Some((IVec::from(&[9]), IVec::from(&[9]))))
-    /// );
-    /// assert_eq!(db.get_gt(&[9]), Ok(None));
-    ///
-    /// db.insert(500u16.to_be_bytes(), vec![10]);
-    /// assert_eq!(
-    ///     db.get_gt(&499u16.to_be_bytes()),
-    ///     Ok(Some((IVec::from(&500u16.to_be_bytes()), IVec::from(&[10]))))
-    /// );
+    /// let start: &[u8] = &[2];
+    /// let end: &[u8] = &[5];
+    /// let sum = db.fold_range(start..end, 0_u64, |acc, _k, v| {
+    ///     acc + u64::from(v[0])
+    /// })?;
+    /// assert_eq!(sum, 2 + 3 + 4);
     /// # Ok(()) }
     /// ```
-    pub fn get_gt<K>(&self, key: K) -> Result<Option<(IVec, IVec)>>
+    #[doc(alias = "merge_range_values")]
+    pub fn fold_range<K, R, A, F>(
+        &self,
+        range: R,
+        init: A,
+        mut f: F,
+    ) -> Result<A>
     where
         K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+        F: FnMut(A, &[u8], &[u8]) -> A,
     {
-        #[cfg(feature = "metrics")]
-        let _measure = Measure::new(&M.tree_get);
-        self.range((ops::Bound::Excluded(key), ops::Bound::Unbounded))
-            .next()
-            .transpose()
+        // `f` takes the accumulator by value, which can only be
+        // moved out of the `FnMut` closure below through an `Option`
+        let mut acc = Some(init);
+        self.for_each_in_range(range, |k, v| {
+            acc = Some(f(acc.take().unwrap(), k, v));
+            ControlFlow::Continue(())
+        })?;
+        Ok(acc.unwrap())
     }
 
-    /// Merge state directly into a given key's value using the
-    /// configured merge operator. This allows state to be written
-    /// into a value directly, without any read-modify-write steps.
-    /// Merge operators can be used to implement arbitrary data
-    /// structures.
-    ///
-    /// Calling `merge` will return an `Unsupported` error if it
-    /// is called without first setting a merge operator function.
-    ///
-    /// Merge operators are shared by all instances of a particular
-    /// `Tree`. Different merge operators may be set on different
-    /// `Tree`s.
+    /// Take a read-only [`Snapshot`] of the `Tree` that keeps
+    /// seeing the data as of this call, unaffected by any
+    /// later writes. Writes to any `Tree` are blocked while
+    /// the snapshot is being taken, which walks every leaf
+    /// once. See [`Snapshot`] for the memory cost of holding
+    /// one open.
     ///
     /// # Examples
     ///
@@ -1098,132 +3090,384 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// use sled::IVec;
+    /// db.insert(b"a", b"1")?;
     ///
-    /// fn concatenate_merge(
-    ///   _key: &[u8],               // the key being merged
-    ///   old_value: Option<&[u8]>,  // the previous value, if one existed
-    ///   merged_bytes: &[u8]        // the new bytes being merged in
-    /// ) -> Option<Vec<u8>> {       // set the new value, return None to delete
-    ///   let mut ret = old_value
-    ///     .map(|ov| ov.to_vec())
-    ///     .unwrap_or_else(|| vec![]);
+    /// let snapshot = db.snapshot()?;
     ///
-    ///   ret.extend_from_slice(merged_bytes);
+    /// db.insert(b"a", b"2")?;
+    /// db.insert(b"b", b"3")?;
     ///
-    ///   Some(ret)
-    /// }
+    /// assert_eq!(snapshot.get(b"a"), Some(sled::IVec::from(b"1")));
+    /// assert!(!snapshot.contains_key(b"b"));
+    /// assert_eq!(snapshot.iter().count(), 1);
+    /// # Ok(()) }
+    /// ```
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        let _cc = concurrency_control::write();
+        let guard = pin();
+        self.snapshot_inner(&guard)
+    }
+
+    // Walks the leaves for `snapshot`. The caller must hold the
+    // write lock from `concurrency_control`, so that no write
+    // lands while the leaves are collected.
+    pub(crate) fn snapshot_inner(&self, guard: &Guard) -> Result<Snapshot> {
+        let mut leaves: Vec<Node> = vec![];
+        let mut key = IVec::default();
+        loop {
+            let view = self.view_for_key(&key, guard)?;
+
+            // a leaf that merged while we walked may start
+            // below leaves we already hold, and covers them
+            while let Some(last) = leaves.last() {
+                if last.lo() < view.lo() {
+                    break;
+                }
+                leaves.pop();
+            }
+
+            // share the immutable parts of the leaf instead of
+            // cloning it, which would merge the overlay
+            leaves.push(Node {
+                overlay: view.overlay.clone(),
+                inner: view.inner.clone(),
+            });
+
+            match view.hi() {
+                Some(hi) => key = hi.into(),
+                None => break,
+            }
+        }
+
+        Ok(Snapshot::from_leaves(leaves))
+    }
+
+    /// Calls `f` with each key and value within `range`, in
+    /// ascending order, until `f` returns
+    /// `ControlFlow::Break`. Unlike [`Tree::range`], keys and
+    /// values are passed as borrowed slices, and no `IVec` is
+    /// created for them, which makes this cheaper for scans
+    /// that only inspect items.
     ///
-    /// db.set_merge_operator(concatenate_merge);
+    /// One leaf is read at a time, and no lock is held while
+    /// `f` runs, so `f` may call back into the `Tree`. Like
+    /// `range`, this is not a point-in-time view: a write made
+    /// by `f` or by another thread may or may not be seen,
+    /// depending on whether its leaf has been reached yet.
     ///
-    /// let k = b"k1";
+    /// # Examples
     ///
-    /// db.insert(k, vec![0]);
-    /// db.merge(k, vec![1]);
-    /// db.merge(k, vec![2]);
-    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![0, 1, 2]))));
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use std::ops::ControlFlow;
     ///
-    /// // Replace previously merged data. The merge function will not be called.
-    /// db.insert(k, vec![3]);
-    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![3]))));
+    /// for i in 0..10_u8 {
+    ///     db.insert(&[i], vec![i; 100])?;
+    /// }
     ///
-    /// // Merges on non-present values will cause the merge function to be called
-    /// // with `old_value == None`. If the merge function returns something (which it
-    /// // does, in this case) a new value will be inserted.
-    /// db.remove(k);
-    /// db.merge(k, vec![4]);
-    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![4]))));
+    /// let mut total = 0;
+    /// db.for_each_in_range(&[2_u8][..].., |key, value| {
+    ///     if key[0] == 5 {
+    ///         return ControlFlow::Break(());
+    ///     }
+    ///     total += value.len();
+    ///     ControlFlow::Continue(())
+    /// })?;
+    /// assert_eq!(total, 300);
     /// # Ok(()) }
     /// ```
-    pub fn merge<K, V>(&self, key: K, value: V) -> Result<Option<IVec>>
+    pub fn for_each_in_range<K, R, F>(&self, range: R, mut f: F) -> Result<()>
     where
         K: AsRef<[u8]>,
-        V: AsRef<[u8]>,
+        R: RangeBounds<K>,
+        F: FnMut(&[u8], &[u8]) -> ControlFlow<()>,
     {
-        let _cc = concurrency_control::read();
+        let Iter { lo, hi, .. } = self.range(range);
+
+        let in_lower_bound = |key: &[u8]| match &lo {
+            ops::Bound::Included(start) => key >= start,
+            ops::Bound::Excluded(start) => key > start,
+            ops::Bound::Unbounded => true,
+        };
+        let in_upper_bound = |key: &[u8]| match &hi {
+            ops::Bound::Included(end) => key <= end,
+            ops::Bound::Excluded(end) => key < end,
+            ops::Bound::Unbounded => true,
+        };
+
+        let mut cursor = match &lo {
+            ops::Bound::Included(start) | ops::Bound::Excluded(start) => {
+                start.clone()
+            }
+            ops::Bound::Unbounded => IVec::default(),
+        };
+        let mut buf = vec![];
+        let mut first_leaf = true;
+
         loop {
-            if let Ok(merge) = self.merge_inner(key.as_ref(), value.as_ref())? {
-                return Ok(merge);
+            // share the leaf's immutable parts so that neither
+            // the guard nor the lock is held while `f` runs
+            let leaf = {
+                let guard = pin();
+                let _cc = concurrency_control::read();
+                let view = self.view_for_key(&cursor, &guard)?;
+                Node {
+                    overlay: view.overlay.clone(),
+                    inner: view.inner.clone(),
+                }
+            };
+
+            // a merge may have widened this leaf to cover keys
+            // that an earlier leaf already passed to `f`, so after
+            // the first leaf only keys at or past the cursor count
+            let already_seen = |key: &[u8]| {
+                if first_leaf {
+                    !in_lower_bound(key)
+                } else {
+                    key < &*cursor
+                }
+            };
+
+            let flow = leaf.for_each_kv(&mut buf, |key, value| {
+                if already_seen(key) {
+                    ControlFlow::Continue(())
+                } else if !in_upper_bound(key) {
+                    ControlFlow::Break(())
+                } else {
+                    f(key, value)
+                }
+            });
+
+            if matches!(flow, ControlFlow::Break(_))
+                || leaf.contains_upper_bound(&hi)
+            {
+                return Ok(());
             }
+            cursor = IVec::from(leaf.hi().unwrap());
+            first_leaf = false;
         }
     }
 
-    pub(crate) fn merge_inner(
+    /// Groups the keys of the `Tree` by their first `prefix_len`
+    /// bytes, and returns the number of keys and the total size
+    /// in bytes of the keys and values in each group. Keys that
+    /// are shorter than `prefix_len` are grouped by the whole
+    /// key. This is useful for seeing how much data each tenant
+    /// holds when tenants are separated by key prefixes.
+    ///
+    /// The `Tree` is read one leaf at a time using
+    /// [`Tree::for_each_in_range`], so only the groups, and not
+    /// the data, are held in memory.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a/1", vec![0; 10])?;
+    /// db.insert(b"a/2", vec![0; 10])?;
+    /// db.insert(b"b/1", vec![0; 5])?;
+    ///
+    /// let stats = db.prefix_stats(2)?;
+    /// assert_eq!(stats[&sled::IVec::from(b"a/")], (2, 26));
+    /// assert_eq!(stats[&sled::IVec::from(b"b/")], (1, 8));
+    /// # Ok(()) }
+    /// ```
+    pub fn prefix_stats(
         &self,
-        key: &[u8],
-        value: &[u8],
-    ) -> Result<Conflictable<Option<IVec>>> {
-        trace!("merging key {:?}", key);
-        #[cfg(feature = "metrics")]
-        let _measure = Measure::new(&M.tree_merge);
-
-        let merge_operator_opt = self.merge_operator.read();
+        prefix_len: usize,
+    ) -> Result<BTreeMap<IVec, (u64, u64)>> {
+        let mut stats: BTreeMap<IVec, (u64, u64)> = BTreeMap::new();
 
-        if merge_operator_opt.is_none() {
-            return Err(Error::Unsupported(
-                "must set a merge operator on this Tree \
-                 before calling merge by calling \
-                 Tree::set_merge_operator"
-            ));
-        }
+        self.for_each_in_range::<&[u8], _, _>(.., |key, value| {
+            let prefix = &key[..key.len().min(prefix_len)];
+            let size = (key.len() + value.len()) as u64;
 
-        let merge_operator = merge_operator_opt.as_ref().unwrap();
+            if let Some((count, bytes)) = stats.get_mut(prefix) {
+                *count += 1;
+                *bytes += size;
+            } else {
+                stats.insert(IVec::from(prefix), (1, size));
+            }
 
-        loop {
-            let guard = pin();
-            let View { pid, node_view, .. } =
-                self.view_for_key(key.as_ref(), &guard)?;
+            ControlFlow::Continue(())
+        })?;
 
-            let (encoded_key, current_value) =
-                node_view.node_kv_pair(key.as_ref());
-            let tmp = current_value.as_ref().map(AsRef::as_ref);
-            let new_opt = merge_operator(key, tmp, value).map(IVec::from);
+        Ok(stats)
+    }
 
-            if new_opt.as_ref().map(AsRef::as_ref) == current_value {
-                // short-circuit no-op write
-                return Ok(Ok(new_opt));
-            }
+    /// Returns the number of bytes that the leaves covering
+    /// `range` occupy on disk, which is useful for seeing the
+    /// storage cost of a logical partition of the keyspace.
+    ///
+    /// Each leaf is stored as a base and a chain of deltas
+    /// that are only folded together when the leaf is
+    /// rewritten, so this counts every one of them. Leaves
+    /// that only partly overlap the range are counted in
+    /// full, and the size of a leaf is counted even if it is
+    /// not resident. Fragments that live in the log are read
+    /// back from disk to learn their length, so this performs
+    /// IO for each leaf in the range, and first waits for any
+    /// of them that are not yet durable to be flushed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..100_u8 {
+    ///     db.insert(&[i], vec![0; 64])?;
+    /// }
+    ///
+    /// let start: &[u8] = &[0];
+    /// let end: &[u8] = &[50];
+    /// let partition = db.stored_size_of_range(start..end)?;
+    /// assert!(partition > 0);
+    /// assert!(partition <= db.stored_size_of_range::<&[u8], _>(..)?);
+    /// # Ok(()) }
+    /// ```
+    #[doc(alias = "compressed_size_of_range")]
+    pub fn stored_size_of_range<K, R>(&self, range: R) -> Result<u64>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let Iter { lo, hi, .. } = self.range(range);
 
-            let mut subscriber_reservation = self.subscribers.reserve(key);
+        let mut cursor = match lo {
+            ops::Bound::Included(start) | ops::Bound::Excluded(start) => start,
+            ops::Bound::Unbounded => IVec::default(),
+        };
+        let mut size = 0;
 
-            let frag = if let Some(ref new) = new_opt {
-                Link::Set(encoded_key, new.clone())
-            } else {
-                Link::Del(encoded_key)
-            };
-            let link =
-                self.context.pagecache.link(pid, node_view.0, frag, &guard)?;
+        loop {
+            let guard = pin();
+            let _cc = concurrency_control::read();
+            let view = self.view_for_key(&cursor, &guard)?;
+            size += self.context.pagecache.stored_size(view.pid, &guard)?;
 
-            if link.is_ok() {
-                if let Some(res) = subscriber_reservation.take() {
-                    let event = subscriber::Event::single_update(
-                        self.clone(),
-                        key.as_ref().into(),
-                        new_opt.clone(),
-                    );
+            if view.contains_upper_bound(&hi) {
+                return Ok(size);
+            }
+            cursor = IVec::from(view.hi().unwrap());
+        }
+    }
 
-                    res.complete(&event);
-                }
+    /// Create an iterator over tuples of keys and values
+    /// where all keys start with the given prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// use sled::IVec;
+    /// db.insert(&[0, 0, 0], vec![0, 0, 0])?;
+    /// db.insert(&[0, 0, 1], vec![0, 0, 1])?;
+    /// db.insert(&[0, 0, 2], vec![0, 0, 2])?;
+    /// db.insert(&[0, 0, 3], vec![0, 0, 3])?;
+    /// db.insert(&[0, 1, 0], vec![0, 1, 0])?;
+    /// db.insert(&[0, 1, 1], vec![0, 1, 1])?;
+    ///
+    /// let prefix: &[u8] = &[0, 0];
+    /// let mut r = db.scan_prefix(prefix);
+    /// assert_eq!(
+    ///     r.next(),
+    ///     Some(Ok((IVec::from(&[0, 0, 0]), IVec::from(&[0, 0, 0]))))
+    /// );
+    /// assert_eq!(
+    ///     r.next(),
+    ///     Some(Ok((IVec::from(&[0, 0, 1]), IVec::from(&[0, 0, 1]))))
+    /// );
+    /// assert_eq!(
+    ///     r.next(),
+    ///     Some(Ok((IVec::from(&[0, 0, 2]), IVec::from(&[0, 0, 2]))))
+    /// );
+    /// assert_eq!(
+    ///     r.next(),
+    ///     Some(Ok((IVec::from(&[0, 0, 3]), IVec::from(&[0, 0, 3]))))
+    /// );
+    /// assert_eq!(r.next(), None);
+    /// # Ok(()) }
+    /// ```
+    pub fn scan_prefix<P>(&self, prefix: P) -> Iter
+    where
+        P: AsRef<[u8]>,
+    {
+        let prefix_ref = prefix.as_ref();
+        let mut upper = prefix_ref.to_vec();
 
-                return Ok(Ok(new_opt));
+        while let Some(last) = upper.pop() {
+            if last < u8::max_value() {
+                upper.push(last + 1);
+                return self.range(prefix_ref..&upper);
             }
-            #[cfg(feature = "metrics")]
-            M.tree_looped();
         }
+
+        self.range(prefix..)
     }
 
-    /// Sets a merge operator for use with the `merge` function.
+    /// Returns `true` if the `Tree` contains at least one key
+    /// that starts with the given prefix. Unlike calling
+    /// `next` on [`Tree::scan_prefix`], this does not build an
+    /// iterator, and usually only looks at the single leaf
+    /// that the prefix falls into.
     ///
-    /// Merge state directly into a given key's value using the
-    /// configured merge operator. This allows state to be written
-    /// into a value directly, without any read-modify-write steps.
-    /// Merge operators can be used to implement arbitrary data
-    /// structures.
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"user/1", vec![1])?;
+    /// db.insert(b"zebra", vec![2])?;
     ///
-    /// # Panics
+    /// assert!(db.contains_prefix(b"user/")?);
+    /// assert!(db.contains_prefix(b"")?);
+    /// assert!(!db.contains_prefix(b"user/2")?);
+    /// assert!(!db.contains_prefix(b"v")?);
+    /// # Ok(()) }
+    /// ```
+    pub fn contains_prefix<P: AsRef<[u8]>>(&self, prefix: P) -> Result<bool> {
+        let prefix = prefix.as_ref();
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let mut lo = IVec::from(prefix);
+        loop {
+            let view = self.view_for_key(&lo, &guard)?;
+            let bound = ops::Bound::Included(lo);
+            if let Some((key, _)) = view.successor(&bound) {
+                return Ok(key.starts_with(prefix));
+            }
+
+            // the leaf holds no keys at or after the prefix, but
+            // keys with the prefix may still continue in the next
+            // leaf if this one ends inside of the prefix's range
+            match view.hi() {
+                Some(hi) if hi.starts_with(prefix) => lo = IVec::from(hi),
+                _ => return Ok(false),
+            }
+        }
+    }
+
+    /// Removes every key that starts with the given prefix,
+    /// returning the number of keys removed.
     ///
-    /// Calling `merge` will panic if no merge operator has been
-    /// configured.
+    /// Rather than writing one deletion per key, each node
+    /// that overlaps the prefix is rewritten once with the
+    /// matching keys left out, and nodes that only hold
+    /// matching keys are replaced with empty ones, so the cost
+    /// of writing scales with the number of affected nodes
+    /// instead of the number of keys.
+    /// Each node is rewritten atomically, but the operation as
+    /// a whole is not: concurrent readers may observe some
+    /// nodes already truncated while others are not yet.
     ///
     /// # Examples
     ///
@@ -1231,53 +3475,171 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// use sled::IVec;
+    /// db.insert(b"tenant_a/1", vec![1])?;
+    /// db.insert(b"tenant_a/2", vec![2])?;
+    /// db.insert(b"tenant_b/1", vec![3])?;
     ///
-    /// fn concatenate_merge(
-    ///   _key: &[u8],               // the key being merged
-    ///   old_value: Option<&[u8]>,  // the previous value, if one existed
-    ///   merged_bytes: &[u8]        // the new bytes being merged in
-    /// ) -> Option<Vec<u8>> {       // set the new value, return None to delete
-    ///   let mut ret = old_value
-    ///     .map(|ov| ov.to_vec())
-    ///     .unwrap_or_else(|| vec![]);
+    /// assert_eq!(db.truncate_prefix(b"tenant_a/")?, 2);
+    /// assert_eq!(db.len(), 1);
+    /// assert!(db.contains_key(b"tenant_b/1")?);
+    /// # Ok(()) }
+    /// ```
+    pub fn truncate_prefix<P>(&self, prefix: P) -> Result<usize>
+    where
+        P: AsRef<[u8]>,
+    {
+        let prefix_ref = prefix.as_ref();
+        let lo = ops::Bound::Included(IVec::from(prefix_ref));
+        let mut upper = prefix_ref.to_vec();
+
+        while let Some(last) = upper.pop() {
+            if last < u8::MAX {
+                upper.push(last + 1);
+                let hi = ops::Bound::Excluded(IVec::from(upper));
+                return self.remove_range_inner(lo, hi, None);
+            }
+        }
+
+        self.remove_range_inner(lo, ops::Bound::Unbounded, None)
+    }
+
+    /// Moves every key that starts with `from` so that it
+    /// starts with `to` instead, keeping its value, and returns
+    /// the number of keys moved.
+    ///
+    /// The matching keys are read up front and then rewritten
+    /// in a single atomic [`Batch`], so readers see either all
+    /// of the old keys or all of the new ones. Because the
+    /// source keys are collected before anything is written,
+    /// overlapping prefixes like `a` and `ab` are moved exactly
+    /// once. Keys already under `to` are overwritten when a
+    /// moved key lands on them. Writes to any `Tree` are
+    /// blocked from the time the keys are read until the batch
+    /// is applied, so no concurrent write to a moved key is
+    /// lost.
     ///
-    ///   ret.extend_from_slice(merged_bytes);
+    /// # Examples
     ///
-    ///   Some(ret)
-    /// }
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"user:123:name", "alice")?;
+    /// db.insert(b"user:123:email", "a@example.com")?;
+    /// db.insert(b"user:124:name", "bob")?;
     ///
-    /// db.set_merge_operator(concatenate_merge);
+    /// assert_eq!(db.rename_prefix(b"user:123:", b"archived:user:123:")?, 2);
+    /// assert!(!db.contains_prefix(b"user:123:")?);
+    /// assert_eq!(
+    ///     db.get(b"archived:user:123:name")?,
+    ///     Some(sled::IVec::from("alice"))
+    /// );
+    /// assert!(db.contains_key(b"user:124:name")?);
+    /// # Ok(()) }
+    /// ```
+    pub fn rename_prefix<F, T>(&self, from: F, to: T) -> Result<usize>
+    where
+        F: AsRef<[u8]>,
+        T: AsRef<[u8]>,
+    {
+        let from = from.as_ref();
+        let to = to.as_ref();
+
+        // hold off other writers from reading the pairs until the
+        // batch is applied, so that no write to a source key can
+        // land in between and be lost when that key is removed
+        let _cc = concurrency_control::write();
+        let mut guard = pin();
+
+        let mut pairs = vec![];
+        let mut cursor = IVec::from(from);
+        let mut buf = vec![];
+        loop {
+            let view = self.view_for_key(&cursor, &guard)?;
+            let flow = view.for_each_kv(&mut buf, |key, value| {
+                if key < &*cursor {
+                    ControlFlow::Continue(())
+                } else if key.starts_with(from) {
+                    pairs.push((IVec::from(key), IVec::from(value)));
+                    ControlFlow::Continue(())
+                } else {
+                    ControlFlow::Break(())
+                }
+            });
+            let more = matches!(flow, ControlFlow::Continue(_));
+            match view.hi() {
+                Some(hi) if more && hi.starts_with(from) => {
+                    cursor = IVec::from(hi);
+                }
+                _ => break,
+            }
+        }
+
+        // all removals go in before any insertion, so that a
+        // moved key landing on another source key is kept
+        let mut batch = Batch::default();
+        for (key, _) in &pairs {
+            batch.remove(key);
+        }
+        for (key, value) in &pairs {
+            let mut new_key = Vec::with_capacity(to.len() + key.len());
+            new_key.extend_from_slice(to);
+            new_key.extend_from_slice(&key[from.len()..]);
+            batch.insert(new_key, value);
+        }
+
+        self.apply_batch_inner(batch, None, None, &mut guard)?;
+        Ok(pairs.len())
+    }
+
+    /// Removes every key within `range`, returning the number
+    /// of keys removed.
     ///
-    /// let k = b"k1";
+    /// Like [`Tree::truncate_prefix`], each node that overlaps
+    /// the range is rewritten once with the matching keys left
+    /// out. Nodes that lie entirely within the range are
+    /// replaced with empty ones in a single step, and emptied
+    /// nodes are merged into their neighbors by the next
+    /// operation that reaches them. Each node is
+    /// rewritten atomically, but the range as a whole is not.
     ///
-    /// db.insert(k, vec![0]);
-    /// db.merge(k, vec![1]);
-    /// db.merge(k, vec![2]);
-    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![0, 1, 2]))));
+    /// # Examples
     ///
-    /// // Replace previously merged data. The merge function will not be called.
-    /// db.insert(k, vec![3]);
-    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![3]))));
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..10_u8 {
+    ///     db.insert(&[i], vec![i])?;
+    /// }
     ///
-    /// // Merges on non-present values will cause the merge function to be called
-    /// // with `old_value == None`. If the merge function returns something (which it
-    /// // does, in this case) a new value will be inserted.
-    /// db.remove(k);
-    /// db.merge(k, vec![4]);
-    /// assert_eq!(db.get(k), Ok(Some(IVec::from(vec![4]))));
+    /// let start: &[u8] = &[2];
+    /// let end: &[u8] = &[5];
+    /// assert_eq!(db.remove_range(start..end)?, 3);
+    /// assert_eq!(db.len(), 7);
+    /// assert!(!db.contains_key(&[3])?);
+    /// assert!(db.contains_key(&[5])?);
     /// # Ok(()) }
     /// ```
-    pub fn set_merge_operator(
-        &self,
-        merge_operator: impl MergeOperator + 'static,
-    ) {
-        let mut mo_write = self.merge_operator.write();
-        *mo_write = Some(Box::new(merge_operator));
+    pub fn remove_range<K, R>(&self, range: R) -> Result<usize>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let Iter { lo, hi, .. } = self.range(range);
+        self.remove_range_inner(lo, hi, None)
     }
 
-    /// Create a double-ended iterator over the tuples of keys and
-    /// values in this tree.
+    /// Removes every key within `range`, returning the removed
+    /// key-value pairs in key order.
+    ///
+    /// This works like [`Tree::remove_range`]: each node that
+    /// overlaps the range has its matching pairs removed and
+    /// returned in one atomic rewrite, so no write can slip
+    /// in between a pair being read and removed. The range as
+    /// a whole is not removed atomically, so keys that are
+    /// concurrently inserted into nodes that were already
+    /// visited are left in place.
     ///
     /// # Examples
     ///
@@ -1285,32 +3647,48 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// use sled::IVec;
-    /// db.insert(&[1], vec![10]);
-    /// db.insert(&[2], vec![20]);
-    /// db.insert(&[3], vec![30]);
-    /// let mut iter = db.iter();
-    /// assert_eq!(
-    ///     iter.next().unwrap(),
-    ///     Ok((IVec::from(&[1]), IVec::from(&[10])))
-    /// );
-    /// assert_eq!(
-    ///     iter.next().unwrap(),
-    ///     Ok((IVec::from(&[2]), IVec::from(&[20])))
-    /// );
+    /// for i in 0..5_u8 {
+    ///     db.insert(&[i], vec![i])?;
+    /// }
+    ///
+    /// let start: &[u8] = &[1];
+    /// let end: &[u8] = &[3];
+    /// let taken = db.take_range(start..end)?;
     /// assert_eq!(
-    ///     iter.next().unwrap(),
-    ///     Ok((IVec::from(&[3]), IVec::from(&[30])))
+    ///     taken,
+    ///     vec![(sled::IVec::from(&[1]), sled::IVec::from(&[1])),
+    ///          (sled::IVec::from(&[2]), sled::IVec::from(&[2]))]
     /// );
-    /// assert_eq!(iter.next(), None);
+    /// assert_eq!(db.len(), 3);
     /// # Ok(()) }
     /// ```
-    pub fn iter(&self) -> Iter {
-        self.range::<Vec<u8>, _>(..)
+    pub fn take_range<K, R>(&self, range: R) -> Result<Vec<(IVec, IVec)>>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        let Iter { lo, hi, .. } = self.range(range);
+        let mut taken = vec![];
+        self.remove_range_inner(lo, hi, Some(&mut taken))?;
+        Ok(taken)
     }
 
-    /// Create a double-ended iterator over tuples of keys and values,
-    /// where the keys fall within the specified range.
+    /// Applies `f` to every key and value within `range`,
+    /// replacing each value with the result, or removing the
+    /// key if `f` returns `None`. Returns the number of keys
+    /// that were changed or removed.
+    ///
+    /// Like [`Tree::remove_range`], each node that overlaps the
+    /// range is rewritten once with all of its updates, instead
+    /// of swapping every key on its own. Each node is rewritten
+    /// atomically, but the range as a whole is not, so a reader
+    /// may see some nodes before and others after the update.
+    ///
+    /// # Note
+    ///
+    /// `f` may be called more than once for the same key if its
+    /// node is changed by another thread in the meantime, and
+    /// only the result of the last call is written.
     ///
     /// # Examples
     ///
@@ -1318,63 +3696,280 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// use sled::IVec;
-    /// db.insert(&[0], vec![0])?;
-    /// db.insert(&[1], vec![10])?;
-    /// db.insert(&[2], vec![20])?;
-    /// db.insert(&[3], vec![30])?;
-    /// db.insert(&[4], vec![40])?;
-    /// db.insert(&[5], vec![50])?;
+    /// for i in 0..5_u8 {
+    ///     db.insert(&[b'c', i], vec![i])?;
+    /// }
     ///
-    /// let start: &[u8] = &[2];
-    /// let end: &[u8] = &[4];
-    /// let mut r = db.range(start..end);
-    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[2]), IVec::from(&[20]))));
-    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[3]), IVec::from(&[30]))));
-    /// assert_eq!(r.next(), None);
+    /// // increment every counter, removing the ones that reach 3
+    /// let changed = db.transform_range(&b"c"[..].., |_key, value| {
+    ///     let next = value[0] + 1;
+    ///     if next == 3 { None } else { Some(vec![next]) }
+    /// })?;
     ///
-    /// let mut r = db.range(start..end).rev();
-    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[3]), IVec::from(&[30]))));
-    /// assert_eq!(r.next().unwrap(), Ok((IVec::from(&[2]), IVec::from(&[20]))));
-    /// assert_eq!(r.next(), None);
+    /// assert_eq!(changed, 5);
+    /// assert_eq!(db.get(&[b'c', 0])?, Some(sled::IVec::from(&[1])));
+    /// assert!(!db.contains_key(&[b'c', 2])?);
+    /// assert_eq!(db.len(), 4);
     /// # Ok(()) }
     /// ```
-    pub fn range<K, R>(&self, range: R) -> Iter
+    #[doc(alias = "merge_range")]
+    pub fn transform_range<K, R, V, F>(
+        &self,
+        range: R,
+        mut f: F,
+    ) -> Result<usize>
     where
         K: AsRef<[u8]>,
         R: RangeBounds<K>,
+        V: Into<IVec>,
+        F: FnMut(&[u8], &[u8]) -> Option<V>,
     {
-        let lo = match range.start_bound() {
-            ops::Bound::Included(start) => {
-                ops::Bound::Included(IVec::from(start.as_ref()))
+        let Iter { mut lo, hi, .. } = self.range(range);
+
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let in_upper_bound = |key: &[u8]| match &hi {
+            ops::Bound::Included(end) => key <= end,
+            ops::Bound::Excluded(end) => key < end,
+            ops::Bound::Unbounded => true,
+        };
+
+        let mut changed = 0;
+
+        loop {
+            let cursor = match &lo {
+                ops::Bound::Included(start) | ops::Bound::Excluded(start) => {
+                    start.clone()
+                }
+                ops::Bound::Unbounded => IVec::default(),
+            };
+
+            let view = self.view_for_key(&cursor, &guard)?;
+
+            let mut node: Node = view.deref().clone();
+            let mut batch = Batch::default();
+            let mut bound = lo.clone();
+
+            while let Some((key, value)) = view.successor(&bound) {
+                if !in_upper_bound(&key) {
+                    break;
+                }
+                let (encoded_key, _) = view.node_kv_pair(&key);
+                match f(&key, &value).map(Into::into) {
+                    Some(new) if new == value => {}
+                    Some(new) => {
+                        self.check_size_limits(&key, Some(&new))?;
+                        node = node.apply(&Link::Set(encoded_key, new.clone()));
+                        batch.insert(key.clone(), new);
+                    }
+                    None => {
+                        node = node.apply(&Link::Del(encoded_key));
+                        batch.remove(key.clone());
+                    }
+                }
+                bound = ops::Bound::Excluded(key);
             }
-            ops::Bound::Excluded(start) => {
-                ops::Bound::Excluded(IVec::from(start.as_ref()))
+
+            if !batch.writes.is_empty() {
+                let subscriber_reservation =
+                    self.subscribers.reserve_batch(&batch);
+
+                let replace = self.context.pagecache.replace(
+                    view.pid,
+                    view.node_view.0,
+                    &node,
+                    &guard,
+                )?;
+
+                if replace.is_err() {
+                    // the node changed underneath us, so
+                    // go around again for the same range.
+                    #[cfg(feature = "metrics")]
+                    M.tree_looped();
+                    continue;
+                }
+
+                changed += batch.writes.len();
+                let written: usize = batch
+                    .writes
+                    .iter()
+                    .map(|(k, v)| k.len() + v.as_ref().map_or(0, |v| v.len()))
+                    .sum();
+                self.context.pagecache.record_logical_write(written);
+
+                if let Some(res) = subscriber_reservation {
+                    let event =
+                        subscriber::Event::single_batch(self.clone(), batch);
+
+                    res.complete(&event);
+                }
             }
-            ops::Bound::Unbounded => ops::Bound::Included(IVec::from(&[])),
+
+            match view.hi() {
+                Some(node_hi) if !view.contains_upper_bound(&hi) => {
+                    lo = ops::Bound::Included(IVec::from(node_hi));
+                }
+                _ => return Ok(changed),
+            }
+        }
+    }
+
+    // Removes the keys between `lo` and `hi` by rewriting each
+    // overlapping node once, returning how many were removed.
+    // The removed pairs are only copied out when `taken` is
+    // given, and an event batch is only built for nodes that
+    // hold a key that a subscriber is watching.
+    fn remove_range_inner(
+        &self,
+        mut lo: ops::Bound<IVec>,
+        hi: ops::Bound<IVec>,
+        mut taken: Option<&mut Vec<(IVec, IVec)>>,
+    ) -> Result<usize> {
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let in_upper_bound = |key: &[u8]| match &hi {
+            ops::Bound::Included(end) => key <= end,
+            ops::Bound::Excluded(end) => key < end,
+            ops::Bound::Unbounded => true,
         };
 
-        let hi = match range.end_bound() {
-            ops::Bound::Included(end) => {
-                ops::Bound::Included(IVec::from(end.as_ref()))
-            }
-            ops::Bound::Excluded(end) => {
-                ops::Bound::Excluded(IVec::from(end.as_ref()))
+        let mut removed = 0;
+        let mut buf = vec![];
+
+        loop {
+            let cursor = match &lo {
+                ops::Bound::Included(start) | ops::Bound::Excluded(start) => {
+                    start.clone()
+                }
+                ops::Bound::Unbounded => IVec::default(),
+            };
+
+            let view = self.view_for_key(&cursor, &guard)?;
+
+            let in_lower_bound = |key: &[u8]| match &lo {
+                ops::Bound::Included(start) => key >= &**start,
+                ops::Bound::Excluded(start) => key > &**start,
+                ops::Bound::Unbounded => true,
+            };
+
+            // visits the pairs of this node that are in the range
+            let for_each_removed =
+                |buf: &mut Vec<u8>, f: &mut dyn FnMut(&[u8], &[u8])| {
+                    let _ = view.for_each_kv(buf, |key, value| {
+                        if !in_upper_bound(key) {
+                            return ControlFlow::Break(());
+                        }
+                        if in_lower_bound(key) {
+                            f(key, value);
+                        }
+                        ControlFlow::Continue(())
+                    });
+                };
+
+            let covers_node =
+                in_lower_bound(view.lo()) && !view.contains_upper_bound(&hi);
+
+            // when the whole leaf is in the range, replace it with
+            // an empty one instead of deleting its keys one at a
+            // time. only the leaves at either end of the range are
+            // rewritten key by key.
+            let mut node = if covers_node {
+                Node::new_from_sorted(
+                    view.lo(),
+                    view.hi(),
+                    false,
+                    view.next,
+                    &[],
+                )
+            } else {
+                view.deref().clone()
+            };
+
+            let mut count = 0;
+            let mut key_bytes = 0;
+            let mut watched = false;
+
+            for_each_removed(&mut buf, &mut |key, _| {
+                if !covers_node {
+                    let (encoded_key, _) = view.node_kv_pair(key);
+                    node = node.apply(&Link::Del(encoded_key));
+                }
+                count += 1;
+                key_bytes += key.len();
+                watched = watched || self.subscribers.watches(key);
+            });
+
+            if count > 0 {
+                let subscriber_reservation = if watched {
+                    let mut batch = Batch::default();
+                    for_each_removed(&mut buf, &mut |key, _| batch.remove(key));
+                    self.subscribers
+                        .reserve_batch(&batch)
+                        .map(|res| (res, batch))
+                } else {
+                    None
+                };
+
+                let replace = self.context.pagecache.replace(
+                    view.pid,
+                    view.node_view.0,
+                    &node,
+                    &guard,
+                )?;
+
+                if replace.is_err() {
+                    // the node changed underneath us, so
+                    // go around again for the same range.
+                    #[cfg(feature = "metrics")]
+                    M.tree_looped();
+                    continue;
+                }
+
+                self.context.pagecache.record_logical_write(key_bytes);
+
+                if let Some((res, batch)) = subscriber_reservation {
+                    let event =
+                        subscriber::Event::single_batch(self.clone(), batch);
+
+                    res.complete(&event);
+                }
+
+                if let Some(taken) = taken.as_mut() {
+                    for_each_removed(&mut buf, &mut |key, value| {
+                        taken.push((key.into(), value.into()));
+                    });
+                }
+
+                removed += count;
             }
-            ops::Bound::Unbounded => ops::Bound::Unbounded,
-        };
 
-        Iter {
-            tree: self.clone(),
-            hi,
-            lo,
-            cached_node: None,
-            going_forward: true,
+            match view.hi() {
+                Some(node_hi) if !view.contains_upper_bound(&hi) => {
+                    lo = ops::Bound::Included(IVec::from(node_hi));
+                }
+                _ => return Ok(removed),
+            }
         }
     }
 
-    /// Create an iterator over tuples of keys and values
-    /// where all keys start with the given prefix.
+    /// Returns the first key and value in the `Tree`, or
+    /// `None` if the `Tree` is empty.
+    pub fn first(&self) -> Result<Option<(IVec, IVec)>> {
+        self.iter().next().transpose()
+    }
+
+    /// Returns the last key and value in the `Tree`, or
+    /// `None` if the `Tree` is empty.
+    pub fn last(&self) -> Result<Option<(IVec, IVec)>> {
+        self.iter().next_back().transpose()
+    }
+
+    /// Returns the first key and value within `range`, or
+    /// `None` if the range holds no items, without removing
+    /// anything. Like [`Tree::range`], an empty or inverted
+    /// range simply yields `None`.
     ///
     /// # Examples
     ///
@@ -1382,62 +3977,35 @@ impl Tree {
     /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
     /// # let config = sled::Config::new().temporary(true);
     /// # let db = config.open()?;
-    /// use sled::IVec;
-    /// db.insert(&[0, 0, 0], vec![0, 0, 0])?;
-    /// db.insert(&[0, 0, 1], vec![0, 0, 1])?;
-    /// db.insert(&[0, 0, 2], vec![0, 0, 2])?;
-    /// db.insert(&[0, 0, 3], vec![0, 0, 3])?;
-    /// db.insert(&[0, 1, 0], vec![0, 1, 0])?;
-    /// db.insert(&[0, 1, 1], vec![0, 1, 1])?;
+    /// for i in 0..10_u8 {
+    ///     db.insert(&[i], vec![i])?;
+    /// }
     ///
-    /// let prefix: &[u8] = &[0, 0];
-    /// let mut r = db.scan_prefix(prefix);
-    /// assert_eq!(
-    ///     r.next(),
-    ///     Some(Ok((IVec::from(&[0, 0, 0]), IVec::from(&[0, 0, 0]))))
-    /// );
-    /// assert_eq!(
-    ///     r.next(),
-    ///     Some(Ok((IVec::from(&[0, 0, 1]), IVec::from(&[0, 0, 1]))))
-    /// );
-    /// assert_eq!(
-    ///     r.next(),
-    ///     Some(Ok((IVec::from(&[0, 0, 2]), IVec::from(&[0, 0, 2]))))
-    /// );
-    /// assert_eq!(
-    ///     r.next(),
-    ///     Some(Ok((IVec::from(&[0, 0, 3]), IVec::from(&[0, 0, 3]))))
-    /// );
-    /// assert_eq!(r.next(), None);
+    /// let start: &[u8] = &[3];
+    /// let end: &[u8] = &[7];
+    /// assert_eq!(&db.first_in_range(start..end)?.unwrap().0, &[3]);
+    /// assert_eq!(&db.last_in_range(start..end)?.unwrap().0, &[6]);
+    /// assert_eq!(&db.last_in_range(start..=end)?.unwrap().0, &[7]);
+    /// assert_eq!(db.first_in_range(end..start)?, None);
     /// # Ok(()) }
     /// ```
-    pub fn scan_prefix<P>(&self, prefix: P) -> Iter
+    pub fn first_in_range<K, R>(&self, range: R) -> Result<Option<(IVec, IVec)>>
     where
-        P: AsRef<[u8]>,
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
     {
-        let prefix_ref = prefix.as_ref();
-        let mut upper = prefix_ref.to_vec();
-
-        while let Some(last) = upper.pop() {
-            if last < u8::max_value() {
-                upper.push(last + 1);
-                return self.range(prefix_ref..&upper);
-            }
-        }
-
-        self.range(prefix..)
-    }
-
-    /// Returns the first key and value in the `Tree`, or
-    /// `None` if the `Tree` is empty.
-    pub fn first(&self) -> Result<Option<(IVec, IVec)>> {
-        self.iter().next().transpose()
+        self.range(range).next().transpose()
     }
 
-    /// Returns the last key and value in the `Tree`, or
-    /// `None` if the `Tree` is empty.
-    pub fn last(&self) -> Result<Option<(IVec, IVec)>> {
-        self.iter().next_back().transpose()
+    /// Returns the last key and value within `range`, or
+    /// `None` if the range holds no items, without removing
+    /// anything. See [`Tree::first_in_range`].
+    pub fn last_in_range<K, R>(&self, range: R) -> Result<Option<(IVec, IVec)>>
+    where
+        K: AsRef<[u8]>,
+        R: RangeBounds<K>,
+    {
+        self.range(range).next_back().transpose()
     }
 
     /// Atomically removes the maximum item in the `Tree` instance.
@@ -1468,14 +4036,11 @@ impl Tree {
         loop {
             if let Some(first_res) = self.iter().next_back() {
                 let first = first_res?;
-                if self
-                    .compare_and_swap::<_, _, &[u8]>(
-                        &first.0,
-                        Some(&first.1),
-                        None,
-                    )?
-                    .is_ok()
-                {
+                if self.compare_and_swap_unit::<_, _, &[u8]>(
+                    &first.0,
+                    Some(&first.1),
+                    None,
+                )? {
                     trace!("pop_max removed item {:?}", first);
                     return Ok(Some(first));
                 }
@@ -1515,14 +4080,11 @@ impl Tree {
         loop {
             if let Some(first_res) = self.iter().next() {
                 let first = first_res?;
-                if self
-                    .compare_and_swap::<_, _, &[u8]>(
-                        &first.0,
-                        Some(&first.1),
-                        None,
-                    )?
-                    .is_ok()
-                {
+                if self.compare_and_swap_unit::<_, _, &[u8]>(
+                    &first.0,
+                    Some(&first.1),
+                    None,
+                )? {
                     trace!("pop_min removed item {:?}", first);
                     return Ok(Some(first));
                 }
@@ -1553,6 +4115,42 @@ impl Tree {
         self.iter().count()
     }
 
+    /// Returns the number of elements in this tree, like
+    /// [`Tree::len`], but by adding up the number of keys
+    /// stored in each leaf instead of reading every key and
+    /// value. This still visits every leaf, paging it in if
+    /// needed, so it is O(leaves) rather than O(1).
+    ///
+    /// Like `len`, the count is not a point-in-time view:
+    /// writes that happen while it runs may or may not be
+    /// counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// db.insert(b"a", vec![0])?;
+    /// db.insert(b"b", vec![1])?;
+    /// assert_eq!(db.len_fast()?, 2);
+    /// # Ok(()) }
+    /// ```
+    pub fn len_fast(&self) -> Result<usize> {
+        let mut ret = 0;
+        let mut cursor = IVec::default();
+        loop {
+            let guard = pin();
+            let _cc = concurrency_control::read();
+            let view = self.view_for_key(&cursor, &guard)?;
+            ret += view.key_count();
+            match view.hi() {
+                Some(hi) => cursor = IVec::from(hi),
+                None => return Ok(ret),
+            }
+        }
+    }
+
     /// Returns `true` if the `Tree` contains no elements.
     pub fn is_empty(&self) -> bool {
         self.iter().next().is_none()
@@ -1599,7 +4197,7 @@ impl Tree {
     ) -> Result<()> {
         trace!("splitting node with pid {}", view.pid);
         // split node
-        let (mut lhs, rhs) = view.deref().split();
+        let (mut lhs, rhs) = view.deref().split(self.context.split_policy);
         let rhs_lo = rhs.lo().to_vec();
 
         // install right side
@@ -1738,9 +4336,22 @@ impl Tree {
         &self,
         pid: PageId,
         guard: &'g Guard,
+    ) -> Result<Option<View<'g>>> {
+        self.view_for_pid_inner(pid, false, guard)
+    }
+
+    fn view_for_pid_inner<'g>(
+        &self,
+        pid: PageId,
+        cold: bool,
+        guard: &'g Guard,
     ) -> Result<Option<View<'g>>> {
         loop {
-            let node_view_opt = self.context.pagecache.get(pid, guard)?;
+            let node_view_opt = if cold {
+                self.context.pagecache.get_cold(pid, guard)?
+            } else {
+                self.context.pagecache.get(pid, guard)?
+            };
             if let Some(node_view) = &node_view_opt {
                 let view = View { node_view: *node_view, pid };
                 if view.merging_child.is_some() {
@@ -1765,12 +4376,26 @@ impl Tree {
     // high because attempts to split it up have made
     // the inherent complexity of the operation more
     // challenging to understand.
-    #[allow(clippy::cognitive_complexity)]
     pub(crate) fn view_for_key<'g, K>(
         &self,
         key: K,
         guard: &'g Guard,
     ) -> Result<View<'g>>
+    where
+        K: AsRef<[u8]>,
+    {
+        self.view_for_key_inner(key, false, guard)
+    }
+
+    // Like `view_for_key`, but a `cold` traversal does not
+    // record its accesses in the cache or evict other pages.
+    #[allow(clippy::cognitive_complexity)]
+    pub(crate) fn view_for_key_inner<'g, K>(
+        &self,
+        key: K,
+        cold: bool,
+        guard: &'g Guard,
+    ) -> Result<View<'g>>
     where
         K: AsRef<[u8]>,
     {
@@ -1825,7 +4450,7 @@ impl Tree {
                 return Err(Error::CollectionNotFound);
             }
 
-            let node_opt = self.view_for_pid(cursor, guard)?;
+            let node_opt = self.view_for_pid_inner(cursor, cold, guard)?;
 
             let view = if let Some(view) = node_opt {
                 // merging_child should be handled in view_for_pid.
@@ -2340,11 +4965,250 @@ impl Tree {
         Ok(())
     }
 
+    /// Walk the leaves of the `Tree` from left to right,
+    /// reporting the bounds and size of each one without
+    /// copying its keys and values. Leaves are paged in as
+    /// they are reached, but are not held on to. Like
+    /// [`Tree::iter`], this is not a point-in-time view, and
+    /// leaves that split or merge during the walk may be
+    /// reported in either shape.
     #[doc(hidden)]
-    pub fn verify_integrity(&self) -> Result<()> {
-        // verification happens in attempt_fmt
-        self.attempt_fmt()?;
-        Ok(())
+    pub fn iter_nodes(&self) -> impl Iterator<Item = Result<NodeInfo>> {
+        let tree = self.clone();
+        let mut next_lo = Some(IVec::default());
+
+        std::iter::from_fn(move || {
+            let lo = next_lo.take()?;
+            let guard = pin();
+            let _cc = concurrency_control::read();
+            let view = match tree.view_for_key(&lo, &guard) {
+                Ok(view) => view,
+                Err(e) => return Some(Err(e)),
+            };
+
+            let hi = view.hi().map(IVec::from);
+            next_lo = hi.clone();
+
+            Some(Ok(NodeInfo {
+                pid: view.pid,
+                lo: view.lo().into(),
+                hi,
+                len: view.count_in_range(
+                    &ops::Bound::Unbounded,
+                    &ops::Bound::Unbounded,
+                ),
+                size: view.rss(),
+            }))
+        })
+    }
+
+    /// Returns up to `n` of this `Tree`'s leaves that are
+    /// currently in the page cache, starting with the ones
+    /// that were accessed least recently. This can be used to
+    /// find the key ranges that an application-level cache
+    /// built on sled should evict first.
+    ///
+    /// The order is approximate: the page cache tracks
+    /// recency in many independent shards and records
+    /// accesses in batches, so recently touched leaves may
+    /// still be reported as cold for a short while. Leaves
+    /// that are not in memory are never reported. Looking
+    /// leaves up here does not count as an access, so calling
+    /// this does not change the order it reports.
+    ///
+    /// This inspects every resident page of the `Db`, so it
+    /// is meant to be called occasionally, not per operation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..1000_u32 {
+    ///     db.insert(&i.to_be_bytes(), vec![0; 64])?;
+    /// }
+    ///
+    /// let coldest = db.coldest_leaves(2)?;
+    /// assert!(coldest.len() <= 2);
+    /// for leaf in coldest {
+    ///     let keys = match leaf.hi {
+    ///         Some(hi) => db.range(leaf.lo..hi),
+    ///         None => db.range(leaf.lo..),
+    ///     };
+    ///     assert_eq!(keys.count(), leaf.len);
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub fn coldest_leaves(&self, n: usize) -> Result<Vec<NodeInfo>> {
+        let guard = pin();
+        let _cc = concurrency_control::read();
+
+        let mut ret = vec![];
+        for pid in self.context.pagecache.coldest_pids() {
+            if ret.len() >= n {
+                break;
+            }
+
+            let node = match self.context.pagecache.get_resident(pid, &guard)
+            {
+                Some(node) if !node.is_index => node,
+                _ => continue,
+            };
+
+            // the page may belong to another tree, or may have
+            // been merged away, so only report it if it is
+            // the leaf that this tree currently uses for its
+            // low key
+            let view = self.view_for_key_inner(node.lo(), true, &guard)?;
+            if view.pid != pid {
+                continue;
+            }
+
+            ret.push(NodeInfo {
+                pid,
+                lo: view.lo().into(),
+                hi: view.hi().map(IVec::from),
+                len: view.count_in_range(
+                    &ops::Bound::Unbounded,
+                    &ops::Bound::Unbounded,
+                ),
+                size: view.rss(),
+            });
+        }
+
+        Ok(ret)
+    }
+
+    /// Walk every level of the `Tree` without modifying it,
+    /// and return each structural problem that was found, or
+    /// an empty `Vec` if the `Tree` is consistent. This checks
+    /// that:
+    ///
+    /// * every node can be read back from storage,
+    /// * the nodes of each level are linked left to right, with
+    ///   each node starting where its left sibling ends, and
+    ///   together cover the whole keyspace,
+    /// * every child starts at the separator key its parent
+    ///   stores for it, and is linked into its level,
+    /// * every key stored in a node falls within its bounds.
+    ///
+    /// Writes to any `Tree` are blocked while this runs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// # let config = sled::Config::new().temporary(true);
+    /// # let db = config.open()?;
+    /// for i in 0..1000_u32 {
+    ///     db.insert(&i.to_be_bytes(), vec![0; 64])?;
+    /// }
+    ///
+    /// assert_eq!(db.verify_integrity()?, vec![]);
+    /// # Ok(()) }
+    /// ```
+    pub fn verify_integrity(&self) -> Result<Vec<Corruption>> {
+        let _cc = concurrency_control::write();
+        let guard = pin();
+
+        let mut corruptions = vec![];
+
+        // maps each pid that the level above points to onto
+        // the separator key that it was stored under
+        let root_pid = self.root.load(Acquire);
+        let mut expected_children = FastMap8::default();
+        expected_children.insert(root_pid, IVec::default());
+        let mut level_start = Some(root_pid);
+
+        while let Some(first_pid) = level_start.take() {
+            let mut referenced_children = FastMap8::default();
+            let mut visited = FastSet8::default();
+            let mut expected_lo = IVec::default();
+            let mut next_pid = Some(first_pid);
+
+            while let Some(pid) = next_pid.take() {
+                if !visited.insert(pid) {
+                    corruptions.push(Corruption::Loop { pid });
+                    break;
+                }
+
+                let view = match self.view_for_pid(pid, &guard) {
+                    Ok(Some(view)) => view,
+                    Ok(None) => {
+                        corruptions.push(Corruption::Missing { pid });
+                        break;
+                    }
+                    Err(Error::Corruption { .. }) => {
+                        corruptions.push(Corruption::Unreadable { pid });
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                };
+
+                if view.lo() != &*expected_lo {
+                    corruptions.push(Corruption::Discontinuity {
+                        pid,
+                        expected_lo: expected_lo.clone(),
+                        actual_lo: view.lo().into(),
+                    });
+                }
+
+                if let Some(separator) = expected_children.remove(&pid) {
+                    if separator != view.lo() {
+                        corruptions.push(Corruption::LowKeyMismatch {
+                            pid,
+                            separator,
+                            lo: view.lo().into(),
+                        });
+                    }
+                }
+
+                let mut bound = ops::Bound::Unbounded;
+                while let Some((key, value)) = view.successor(&bound) {
+                    let above_hi = match view.hi() {
+                        Some(hi) => &*key >= hi,
+                        None => false,
+                    };
+                    if &*key < view.lo() || above_hi {
+                        corruptions.push(Corruption::KeyOutOfBounds {
+                            pid,
+                            key: key.clone(),
+                        });
+                    }
+
+                    if view.is_index {
+                        let mut pid_bytes = [0; 8];
+                        pid_bytes.copy_from_slice(&value);
+                        let child_pid = u64::from_le_bytes(pid_bytes);
+                        if pid == first_pid && level_start.is_none() {
+                            level_start = Some(child_pid);
+                        }
+                        referenced_children.insert(child_pid, key.clone());
+                    }
+
+                    bound = ops::Bound::Excluded(key);
+                }
+
+                match (view.hi(), view.next) {
+                    (Some(hi), Some(next)) => {
+                        expected_lo = hi.into();
+                        next_pid = Some(next.get());
+                    }
+                    (None, None) => {}
+                    _ => {
+                        corruptions.push(Corruption::BrokenSiblingLink { pid })
+                    }
+                }
+            }
+
+            for pid in expected_children.keys() {
+                corruptions.push(Corruption::Unlinked { pid: *pid });
+            }
+            expected_children = referenced_children;
+        }
+
+        Ok(corruptions)
     }
 
     // format and verify tree integrity
@@ -2498,6 +5362,85 @@ impl Debug for Tree {
     }
 }
 
+/// The bounds and size of a leaf, as reported by
+/// [`Tree::coldest_leaves`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeInfo {
+    /// The page id of the leaf.
+    pub pid: PageId,
+    /// The inclusive low key of the leaf.
+    pub lo: IVec,
+    /// The exclusive high key of the leaf, or `None` for the
+    /// last leaf.
+    pub hi: Option<IVec>,
+    /// The number of keys stored in the leaf.
+    pub len: usize,
+    /// The approximate in-memory size of the leaf in bytes.
+    pub size: u64,
+}
+
+/// A structural problem found by [`Tree::verify_integrity`].
+/// Nodes are identified by their page id, which is only
+/// meaningful for triage and log correlation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Corruption {
+    /// The node could not be read back from storage.
+    Unreadable {
+        /// The page id of the node.
+        pid: PageId,
+    },
+    /// The node is referenced by the tree, but has been freed.
+    Missing {
+        /// The page id of the node.
+        pid: PageId,
+    },
+    /// The node was reached twice while walking its level.
+    Loop {
+        /// The page id of the node.
+        pid: PageId,
+    },
+    /// The node does not start where its left sibling ends, or
+    /// it is the first node of its level and does not start at
+    /// the beginning of the keyspace.
+    Discontinuity {
+        /// The page id of the node.
+        pid: PageId,
+        /// The high key of the left sibling, or the empty key.
+        expected_lo: IVec,
+        /// The low key of the node.
+        actual_lo: IVec,
+    },
+    /// The node has a high key but no right sibling, or a right
+    /// sibling but no high key, leaving its level unterminated.
+    BrokenSiblingLink {
+        /// The page id of the node.
+        pid: PageId,
+    },
+    /// The node's low key differs from the separator key that
+    /// its parent stores for it.
+    LowKeyMismatch {
+        /// The page id of the node.
+        pid: PageId,
+        /// The separator key stored in the parent.
+        separator: IVec,
+        /// The low key of the node.
+        lo: IVec,
+    },
+    /// The node stores a key outside of its bounds.
+    KeyOutOfBounds {
+        /// The page id of the node.
+        pid: PageId,
+        /// The out of bounds key.
+        key: IVec,
+    },
+    /// The node is referenced by its parent, but is not linked
+    /// into its level.
+    Unlinked {
+        /// The page id of the node.
+        pid: PageId,
+    },
+}
+
 /// Compare and swap result.
 ///
 /// It returns `Ok(Ok(()))` if operation finishes successfully and
@@ -2515,6 +5458,66 @@ impl From<Error> for CompareAndSwapResult {
     }
 }
 
+/// Conditional batch result.
+///
+/// It returns `Ok(Ok(()))` if the batch was applied, and
+/// `Ok(Err(mismatches))` with each key whose expected value
+/// did not match, along with its current value, otherwise.
+pub type ConditionalBatchResult =
+    Result<std::result::Result<(), Vec<(IVec, Option<IVec>)>>>;
+
+/// An opaque marker for a durable point in the log,
+/// returned by [`Tree::checkpoint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Checkpoint {
+    lsn: Lsn,
+}
+
+impl Checkpoint {
+    /// Encodes the checkpoint so that it can be persisted
+    /// outside of sled.
+    pub const fn to_bytes(&self) -> [u8; 8] {
+        self.lsn.to_be_bytes()
+    }
+
+    /// Decodes a checkpoint previously encoded with
+    /// [`Checkpoint::to_bytes`].
+    pub const fn from_bytes(bytes: [u8; 8]) -> Checkpoint {
+        Checkpoint { lsn: Lsn::from_be_bytes(bytes) }
+    }
+}
+
+/// The outcome of [`Tree::flush_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlushResult {
+    /// The number of bytes written during the flush.
+    pub bytes: usize,
+    /// The durable point in the log once the flush completed.
+    pub durable_through: Checkpoint,
+}
+
+/// A flush started by [`Tree::flush_in_background`].
+#[derive(Debug)]
+pub struct FlushHandle {
+    promise: OneShot<Result<usize>>,
+}
+
+impl FlushHandle {
+    /// Returns `true` once the flush has finished, whether it
+    /// succeeded or not, without blocking.
+    pub fn is_complete(&self) -> bool {
+        self.promise.is_filled()
+    }
+
+    /// Blocks until the flush has finished, returning the
+    /// number of bytes it wrote.
+    pub fn wait(self) -> Result<usize> {
+        self.promise.wait().unwrap_or(Err(Error::ReportableBug(
+            "threadpool failed to complete action before shutdown",
+        )))
+    }
+}
+
 /// Compare and swap error.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct CompareAndSwapError {
@@ -2531,3 +5534,27 @@ impl fmt::Display for CompareAndSwapError {
 }
 
 impl std::error::Error for CompareAndSwapError {}
+
+/// The outcome of [`Tree::cas`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CasOutcome {
+    /// The swap happened, replacing `previous`, which was
+    /// `None` if the key was not present.
+    Applied {
+        /// The value the key held right before the swap.
+        previous: Option<IVec>,
+    },
+    /// The swap did not happen, because the key held
+    /// `current` rather than the expected value.
+    Mismatch {
+        /// The value which caused the swap to fail.
+        current: Option<IVec>,
+    },
+}
+
+impl CasOutcome {
+    /// Returns `true` if the swap happened.
+    pub const fn is_applied(&self) -> bool {
+        matches!(self, CasOutcome::Applied { .. })
+    }
+}
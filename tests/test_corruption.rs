@@ -0,0 +1,28 @@
+#![cfg(feature = "failpoints")]
+mod common;
+
+use sled::*;
+
+// This lives in its own test binary because failpoints are
+// global to the process, so a test running concurrently in
+// the same process could trip the failpoint instead.
+#[test]
+#[cfg_attr(miri, ignore)]
+fn unreadable_page_fails_the_read() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    db.insert(b"a", b"1")?;
+    db.flush()?;
+    db.page_out_all()?;
+
+    fail::set("pull torn message", 1);
+    let result = db.get(b"a");
+    fail::reset();
+    assert!(matches!(result, Err(Error::Corruption { .. })), "{:?}", result);
+
+    // the page is read again on the next access
+    assert_eq!(db.get(b"a")?, Some(IVec::from(b"1")));
+
+    Ok(())
+}
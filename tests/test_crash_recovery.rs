@@ -154,7 +154,7 @@ fn verify(tree: &sled::Tree) -> (u32, u32) {
         );
     }
 
-    tree.verify_integrity().unwrap();
+    assert_eq!(tree.verify_integrity().unwrap(), vec![]);
 
     (contiguous, highest)
 }
@@ -242,7 +242,7 @@ fn verify_batches(tree: &sled::Tree) -> u32 {
         );
     }
 
-    tree.verify_integrity().unwrap();
+    assert_eq!(tree.verify_integrity().unwrap(), vec![]);
 
     first_value
 }
@@ -427,7 +427,7 @@ fn run_crash_iter() {
     let config = Config::new().path(ITER_DIR).flush_every_ms(Some(1));
 
     let t = config.open().unwrap();
-    t.verify_integrity().unwrap();
+    assert_eq!(t.verify_integrity().unwrap(), vec![]);
 
     const INDELIBLE: [&[u8]; 16] = [
         &[0u8],
@@ -588,7 +588,7 @@ fn run_crash_tx() {
 
     let config = Config::new().flush_every_ms(Some(1)).path(TX_DIR);
     let db = config.open().unwrap();
-    db.verify_integrity().unwrap();
+    assert_eq!(db.verify_integrity().unwrap(), vec![]);
 
     db.insert(b"k1", b"cats").unwrap();
     db.insert(b"k2", b"dogs").unwrap();
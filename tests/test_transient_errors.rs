@@ -0,0 +1,49 @@
+#![cfg(feature = "failpoints")]
+mod common;
+
+use sled::*;
+
+// This lives in its own test binary because failpoints are
+// global to the process, so a test running concurrently in
+// the same process could trip the failpoint instead.
+#[test]
+#[cfg_attr(miri, ignore)]
+fn clear_transient_error_rewrites_failed_buffers() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true).flush_every_ms(None);
+    let db = config.open()?;
+    db.insert(b"a", b"1")?;
+
+    fail::set("buffer write transient", 1);
+    let error = db.flush().unwrap_err();
+    assert!(error.is_transient());
+
+    // the error sticks until it is cleared
+    assert!(db.flush().unwrap_err().is_transient());
+
+    db.clear_transient_error()?;
+    db.insert(b"b", b"2")?;
+    db.flush()?;
+    drop(db);
+
+    let db = config.open()?;
+    assert_eq!(db.get(b"a")?, Some(IVec::from(b"1")));
+    assert_eq!(db.get(b"b")?, Some(IVec::from(b"2")));
+
+    Ok(())
+}
+
+#[test]
+fn only_environmental_io_errors_are_transient() {
+    use std::io;
+
+    let transient = |e: io::Error| Error::from(e).is_transient();
+
+    assert!(transient(io::ErrorKind::Interrupted.into()));
+    assert!(transient(io::ErrorKind::WouldBlock.into()));
+
+    assert!(!transient(io::Error::from_raw_os_error(libc::EIO)));
+    assert!(!transient(io::ErrorKind::Other.into()));
+    assert!(!Error::Unsupported("unsupported").is_transient());
+}
@@ -4,7 +4,7 @@ mod tree;
 use std::{
     sync::{
         atomic::{AtomicUsize, Ordering::SeqCst},
-        Arc, Barrier,
+        Arc, Barrier, Mutex,
     },
     time::Duration,
 };
@@ -183,6 +183,7 @@ fn very_large_reverse_tree_iterator() {
         .temporary(true)
         .flush_every_ms(Some(1))
         .segment_size(256)
+        .max_key_size(2 * 1024 * 1024)
         .open()
         .unwrap();
 
@@ -266,6 +267,49 @@ fn concurrent_tree_pops() -> sled::Result<()> {
     Ok(())
 }
 
+#[test]
+#[cfg(not(miri))] // can't create threads
+fn concurrent_get_or_insert_with() -> sled::Result<()> {
+    use std::thread;
+
+    let db = sled::Config::new().temporary(true).open()?;
+    db.insert(b"present", b"old")?;
+
+    let calls = Arc::new(AtomicUsize::new(0));
+    let existing = db.get_or_insert_with(b"present", || {
+        calls.fetch_add(1, SeqCst);
+        b"new".to_vec()
+    })?;
+    assert_eq!(existing, IVec::from(b"old"));
+    assert_eq!(calls.load(SeqCst), 0);
+
+    let mut threads = vec![];
+
+    // every thread must observe the single value that won
+    let barrier = Arc::new(Barrier::new(5));
+    for i in 0..5_u8 {
+        let barrier = barrier.clone();
+        let calls = calls.clone();
+        let db = db.clone();
+        threads.push(thread::spawn(move || {
+            barrier.wait();
+            db.get_or_insert_with(b"absent", || {
+                calls.fetch_add(1, SeqCst);
+                vec![i]
+            })
+            .unwrap()
+        }));
+    }
+
+    let values: Vec<IVec> =
+        threads.into_iter().map(|t| t.join().unwrap()).collect();
+    let winner = db.get(b"absent")?.unwrap();
+    assert!(values.iter().all(|v| *v == winner));
+    assert!((1..=5).contains(&calls.load(SeqCst)));
+
+    Ok(())
+}
+
 #[test]
 #[cfg(not(miri))] // can't create threads
 fn concurrent_tree_ops() {
@@ -1073,6 +1117,1830 @@ fn contains_tree() {
     assert_eq!(false, db.contains_tree("tree 1"));
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn multi_get_sorted_spans_nodes() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).flush_every_ms(None).open()?;
+
+    // only even keys are present, and enough of them to split
+    for i in (0..N).step_by(2) {
+        db.insert(kv(i), kv(i))?;
+    }
+
+    let keys: Vec<Vec<u8>> = (0..N).rev().chain(0..N).map(kv).collect();
+    let results = db.multi_get_sorted(&keys)?;
+
+    assert_eq!(results.len(), N);
+    for (i, (key, value)) in results.into_iter().enumerate() {
+        assert_eq!(key, kv(i));
+        assert_eq!(value, db.get(&key)?);
+        assert_eq!(value.is_some(), i % 2 == 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn truncate_prefix_spans_nodes() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true).flush_every_ms(None);
+    let db = config.open()?;
+
+    for tenant in 0..3_u8 {
+        for i in 0..N {
+            let mut k = vec![tenant];
+            k.extend(kv(i));
+            db.insert(k, kv(i))?;
+        }
+    }
+
+    let mut subscriber = db.watch_prefix(vec![1]);
+
+    assert_eq!(db.truncate_prefix(vec![1])?, N);
+    assert_eq!(db.truncate_prefix(vec![1])?, 0);
+    assert_eq!(db.len(), 2 * N);
+    assert!(db.scan_prefix(vec![1]).next().is_none());
+
+    let mut removed_events = 0;
+    while let Ok(event) = subscriber.next_timeout(Duration::from_millis(10)) {
+        for (_, key, value) in &event {
+            assert_eq!(key[0], 1);
+            assert!(value.is_none());
+            removed_events += 1;
+        }
+    }
+    assert_eq!(removed_events, N);
+
+    db.flush()?;
+    drop(subscriber);
+    drop(db);
+
+    let db = config.open()?;
+    assert_eq!(db.len(), 2 * N);
+    assert!(db.scan_prefix(vec![1]).next().is_none());
+    assert_eq!(db.scan_prefix(vec![2]).count(), N);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn cold_reads_after_recovery() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new()
+        .temporary(true)
+        .cache_capacity(1024)
+        .flush_every_ms(None);
+    let db = config.open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), vec![i as u8; 64])?;
+    }
+
+    db.flush()?;
+    drop(db);
+
+    // nodes are paged in lazily after recovery,
+    // so these reads go to disk without being cached.
+    let db = config.open()?;
+
+    for i in (0..N).step_by(7) {
+        assert_eq!(db.get_cold(kv(i))?, Some(IVec::from(vec![i as u8; 64])));
+    }
+
+    let mut count = 0;
+    for (i, kv_res) in db.scan_cold::<&[u8], _>(..).enumerate() {
+        let (k, v) = kv_res?;
+        assert_eq!(k, kv(i));
+        assert_eq!(v, vec![i as u8; 64]);
+        count += 1;
+    }
+    assert_eq!(count, N);
+    assert_eq!(db.scan_cold::<&[u8], _>(..).rev().count(), N);
+
+    // regular reads still see everything afterwards
+    assert_eq!(db.iter().count(), N);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(not(miri))] // can't create threads
+fn concurrent_compare_and_append() -> Result<()> {
+    common::setup_logger();
+
+    let db = Arc::new(Config::new().temporary(true).open()?);
+
+    let threads: Vec<_> = (0..N_THREADS)
+        .map(|t| {
+            let db = db.clone();
+            std::thread::spawn(move || -> Result<()> {
+                for _ in 0..N_PER_THREAD {
+                    let mut expected = 0;
+                    while let Err(actual) =
+                        db.compare_and_append(b"log", expected, [t as u8])?
+                    {
+                        expected = actual;
+                    }
+                }
+                Ok(())
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap()?;
+    }
+
+    let log = db.get(b"log")?.unwrap();
+    assert_eq!(log.len(), N);
+    for t in 0..N_THREADS {
+        let count = log.iter().filter(|b| **b == t as u8).count();
+        assert_eq!(count, N_PER_THREAD);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn merge_operator_deletes_and_requires_registration() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true);
+    let db = config.open()?;
+
+    match db.merge(b"counter", [1]) {
+        Err(Error::Unsupported(_)) => {}
+        other => panic!("expected Unsupported, got {:?}", other),
+    }
+
+    // decrements a single-byte counter, removing it at zero
+    fn decrement(
+        _key: &[u8],
+        old: Option<&[u8]>,
+        by: &[u8],
+    ) -> Option<Vec<u8>> {
+        let remaining = old.map_or(0, |o| o[0]).saturating_sub(by[0]);
+        if remaining == 0 {
+            None
+        } else {
+            Some(vec![remaining])
+        }
+    }
+
+    db.set_merge_operator(decrement);
+    db.insert(b"counter", vec![3])?;
+
+    assert_eq!(db.merge(b"counter", [1])?, Some(IVec::from(&[2])));
+    assert_eq!(db.merge(b"counter", [2])?, None);
+    assert!(!db.contains_key(b"counter")?);
+
+    db.insert(b"counter", vec![5])?;
+    db.merge(b"counter", [1])?;
+    db.flush()?;
+    drop(db);
+
+    let db = config.open()?;
+    assert_eq!(db.get(b"counter")?, Some(IVec::from(&[4])));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn compact_after_deleting_half() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true).flush_every_ms(None);
+    let db = config.open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), vec![0; 256])?;
+    }
+    for i in (0..N).step_by(2) {
+        db.remove(kv(i))?;
+    }
+
+    db.compact()?;
+
+    assert_eq!(db.len(), N / 2);
+    drop(db);
+
+    let db = config.open()?;
+    assert_eq!(db.len(), N / 2);
+    for i in 0..N {
+        assert_eq!(db.contains_key(kv(i))?, i % 2 == 1);
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn multi_get_preserves_input_order() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in (0..N).step_by(2) {
+        db.insert(kv(i), kv(i))?;
+    }
+
+    let keys: Vec<usize> = (0..N).rev().collect();
+    let values = db.multi_get(keys.iter().map(|&i| kv(i)))?;
+
+    assert_eq!(values.len(), N);
+    for (i, value) in keys.into_iter().zip(values) {
+        if i % 2 == 0 {
+            assert_eq!(value, Some(IVec::from(kv(i))));
+        } else {
+            assert_eq!(value, None);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn eviction_callback_fires_under_scan() -> Result<()> {
+    common::setup_logger();
+
+    const KEYS: u64 = 10_000;
+
+    let evicted = Arc::new(AtomicUsize::new(0));
+    let evicted2 = evicted.clone();
+
+    let config = Config::new()
+        .temporary(true)
+        .cache_capacity(4096)
+        .on_evict(move |_pid, _size| {
+            evicted2.fetch_add(1, SeqCst);
+        });
+    let db = config.open()?;
+
+    for i in 0..KEYS {
+        db.insert(i.to_be_bytes(), vec![0; 256])?;
+    }
+
+    // accesses are applied to the cache in batches, so
+    // scan enough times for the batches to fill up
+    for _ in 0..10 {
+        assert_eq!(db.iter().count() as u64, KEYS);
+    }
+
+    let stats = db.cache_stats();
+    assert!(evicted.load(SeqCst) > 0);
+    assert_eq!(stats.evictions, evicted.load(SeqCst) as u64);
+    assert!(stats.hits > 0);
+    assert!(stats.misses > 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn range_count_matches_iteration() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), vec![0; 64])?;
+    }
+    for i in (0..N).step_by(3) {
+        db.remove(kv(i))?;
+    }
+
+    assert_eq!(db.len(), db.iter().fold(0, |acc, _| acc + 1));
+
+    for (lo, hi) in [(0, N - 1), (1, 2), (5, 500), (N / 3, N / 2), (7, 7)] {
+        let expected = (lo..hi).filter(|i| i % 3 != 0).count();
+        assert_eq!(db.range(kv(lo)..kv(hi)).count(), expected);
+
+        let expected = (lo..=hi).filter(|i| i % 3 != 0).count();
+        assert_eq!(db.range(kv(lo)..=kv(hi)).count(), expected);
+    }
+
+    let mut iter = db.range(kv(10)..kv(200));
+    iter.next().unwrap()?;
+    iter.next_back().unwrap()?;
+    let (lower, upper) = iter.size_hint();
+    let remaining = (10..200).filter(|i| i % 3 != 0).count() - 2;
+    assert!(lower <= remaining);
+    assert_eq!(upper, None);
+    assert_eq!(iter.count(), remaining);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn parallel_checksum_matches_serial() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    let tree = db.open_tree("other")?;
+
+    for i in 0..N {
+        db.insert(kv(i), vec![1; 64])?;
+        tree.insert(kv(i), kv(i))?;
+    }
+
+    let mut names = db.tree_names();
+    names.sort();
+
+    let mut hasher = crc32fast::Hasher::new();
+    for name in names {
+        hasher.update(&name);
+        for kv_res in &db.open_tree(name)? {
+            let (k, v) = kv_res?;
+            hasher.update(&k);
+            hasher.update(&v);
+        }
+    }
+
+    assert_eq!(db.checksum()?, hasher.finalize());
+
+    let mut hasher = crc32fast::Hasher::new();
+    for kv_res in tree.range(kv(10)..kv(N / 2)) {
+        let (k, v) = kv_res?;
+        hasher.update(&k);
+        hasher.update(&v);
+    }
+
+    assert_eq!(tree.checksum_range(kv(10)..kv(N / 2))?, hasher.finalize());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn load_sorted_builds_usable_tree() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true).flush_every_ms(None);
+    let db = config.open()?;
+    let tree = db.open_tree("loaded")?;
+
+    let unsorted = vec![(vec![2], vec![2]), (vec![1], vec![1])];
+    assert!(tree.load_sorted(unsorted).is_err());
+    assert!(tree.is_empty());
+
+    let mut expected = std::collections::BTreeMap::new();
+    for i in 0..10_000_u32 {
+        let key = format!("key {} {}", i % 10, i).into_bytes();
+        expected.insert(key, i.to_be_bytes().to_vec());
+    }
+
+    assert_eq!(tree.load_sorted(expected.clone())?, expected.len());
+    assert!(tree.load_sorted(vec![(b"z", b"z")]).is_err());
+
+    let check = |tree: &Tree, expected: &std::collections::BTreeMap<_, _>| {
+        let forward: Vec<(Vec<u8>, Vec<u8>)> = tree
+            .iter()
+            .map(|r| r.map(|(k, v)| (k.to_vec(), v.to_vec())))
+            .collect::<Result<_>>()
+            .unwrap();
+        let reverse: Vec<(Vec<u8>, Vec<u8>)> = tree
+            .iter()
+            .rev()
+            .map(|r| r.map(|(k, v)| (k.to_vec(), v.to_vec())))
+            .collect::<Result<_>>()
+            .unwrap();
+        let expected: Vec<(Vec<u8>, Vec<u8>)> =
+            expected.clone().into_iter().collect();
+
+        assert_eq!(forward, expected);
+        assert_eq!(reverse.into_iter().rev().collect::<Vec<_>>(), expected);
+        for (k, v) in &expected {
+            assert_eq!(tree.get(k).unwrap().unwrap(), &**v);
+        }
+    };
+
+    check(&tree, &expected);
+
+    // the loaded nodes must keep working with splits and merges
+    for i in 0..2_000_u32 {
+        let key = format!("key {} {}a", i % 10, i).into_bytes();
+        tree.insert(&key, vec![])?;
+        expected.insert(key, vec![]);
+    }
+    let removed: Vec<Vec<u8>> = expected.keys().step_by(2).cloned().collect();
+    for key in removed {
+        tree.remove(&key)?;
+        expected.remove(&key);
+    }
+
+    check(&tree, &expected);
+
+    drop(tree);
+    drop(db);
+
+    let db = config.open()?;
+    let tree = db.open_tree("loaded")?;
+    check(&tree, &expected);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn range_pagination_with_cursor() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true);
+    let db = config.open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), kv(i))?;
+    }
+
+    let (first_page, mut cursor) = db.range(kv(10)..=kv(500)).take_cursor(7)?;
+    let mut seen: Vec<IVec> = first_page.into_iter().map(|(k, _)| k).collect();
+
+    // persist the cursor across a restart
+    let saved = cursor.take().unwrap().to_bytes();
+    drop(db);
+    let db = config.open()?;
+    let mut cursor = Cursor::from_bytes(&saved);
+
+    while let Some(c) = cursor {
+        let (page, next) = db.range_from_cursor(&c, 7)?;
+        assert!(!page.is_empty());
+        assert!(page.len() == 7 || next.is_none());
+        seen.extend(page.into_iter().map(|(k, _)| k));
+        cursor = next;
+    }
+
+    let expected: Vec<IVec> = (10..=500).map(|i| IVec::from(kv(i))).collect();
+    assert_eq!(seen, expected);
+
+    assert_eq!(Cursor::from_bytes(&[9; 3]), None);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn node_stats_track_residency() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true);
+    let db = config.open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), vec![0; 64])?;
+    }
+    drop(db);
+
+    // opening may page nodes in, for instance to verify them when
+    // the event log is enabled, so start from a known state
+    let db = config.open()?;
+    db.page_out_all()?;
+    let before = db.node_stats();
+    assert!(before.paged_out > 0);
+
+    assert_eq!(db.iter().count(), N);
+
+    let after = db.node_stats();
+    assert!(after.resident > before.resident);
+    assert!(after.average_resident_size() > 0);
+    assert_eq!(after.size_histogram.iter().sum::<u64>(), after.resident);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn concurrent_fetch_add() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    let threads: Vec<_> = (0..4)
+        .map(|_| {
+            let db = db.clone();
+            std::thread::spawn(move || {
+                for _ in 0..250 {
+                    db.fetch_add(b"counter", 2).unwrap();
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    assert_eq!(db.fetch_sub(b"counter", 1000)?, 2000);
+    assert_eq!(db.get(b"counter")?.unwrap(), 1000_u64.to_be_bytes());
+    assert_eq!(db.fetch_sub(b"counter", 1001)?, 1000);
+    assert_eq!(db.fetch_add(b"counter", 1)?, u64::MAX);
+
+    db.insert(b"short", &[1_u8, 2, 3])?;
+    assert!(db.fetch_max(b"short", 9).is_err());
+    assert_eq!(db.get(b"short")?.unwrap(), [1_u8, 2, 3]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn snapshot_ignores_later_writes() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), kv(i))?;
+    }
+
+    let snapshot = db.snapshot()?;
+
+    for i in 0..N {
+        if i % 2 == 0 {
+            db.remove(kv(i))?;
+        } else {
+            db.insert(kv(i), b"changed")?;
+        }
+    }
+    db.insert(b"new", b"new")?;
+
+    assert_eq!(snapshot.iter().count(), N);
+    for (i, (k, v)) in snapshot.iter().enumerate() {
+        assert_eq!(k, kv(i));
+        assert_eq!(v, kv(i));
+    }
+    for i in 0..N {
+        assert_eq!(snapshot.get(kv(i)), Some(kv(i).into()));
+    }
+    assert!(!snapshot.contains_key(b"new"));
+
+    let ranged: Vec<_> =
+        snapshot.range(kv(10)..kv(20)).map(|(k, _)| k).collect();
+    let expected: Vec<IVec> = (10..20).map(|i| kv(i).into()).collect();
+    assert_eq!(ranged, expected);
+    assert_eq!(snapshot.range(kv(10)..=kv(20)).count(), 11);
+    assert_eq!(snapshot.range(kv(N - 1)..).count(), 1);
+
+    assert_eq!(db.iter().count(), N / 2 + 1);
+
+    Ok(())
+}
+
+#[test]
+fn first_and_last_in_range() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), kv(i))?;
+    }
+
+    for (lo, hi) in [(0, N - 1), (10, 20), (N / 2, N / 2 + 1)] {
+        let first = db.first_in_range(kv(lo)..kv(hi))?.unwrap();
+        assert_eq!(first.0, kv(lo));
+        let last = db.last_in_range(kv(lo)..kv(hi))?.unwrap();
+        assert_eq!(last.0, kv(hi - 1));
+        let last = db.last_in_range(kv(lo)..=kv(hi))?.unwrap();
+        assert_eq!(last.0, kv(hi));
+        let first = db
+            .first_in_range::<Vec<u8>, _>((
+                std::ops::Bound::Excluded(kv(lo)),
+                std::ops::Bound::Unbounded,
+            ))?
+            .unwrap();
+        assert_eq!(first.0, kv(lo + 1));
+    }
+
+    assert_eq!(db.first_in_range(kv(5)..kv(5))?, None);
+    assert_eq!(db.last_in_range(kv(5)..kv(5))?, None);
+    assert_eq!(db.last_in_range(kv(6)..kv(5))?, None);
+    assert_eq!(db.last_in_range::<&[u8], _>(..)?, db.last()?);
+
+    db.remove(kv(N - 1))?;
+    assert_eq!(db.last_in_range(kv(N - 2)..)?.unwrap().0, kv(N - 2));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn verify_integrity_after_splits_and_merges() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    for i in 0..20_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 16][..])?;
+    }
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    for i in (0..20_000_u32).filter(|i| i % 7 != 0) {
+        db.remove(&i.to_be_bytes())?;
+    }
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    let sorted = db.open_tree(b"sorted")?;
+    sorted.load_sorted((0..20_000_u32).map(|i| (i.to_be_bytes(), vec![1])))?;
+    assert_eq!(sorted.verify_integrity()?, vec![]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn append_optimized_split_policy_fills_leaves() -> Result<()> {
+    common::setup_logger();
+
+    let node_count = |policy| -> Result<u64> {
+        let db = Config::new()
+            .temporary(true)
+            .split_policy(policy)
+            .open()?;
+        for i in 0..10_000_u32 {
+            db.insert(&i.to_be_bytes(), &[0; 64][..])?;
+        }
+        assert_eq!(db.verify_integrity()?, vec![]);
+        assert_eq!(db.len(), 10_000);
+        let stats = db.node_stats();
+        Ok(stats.resident + stats.paged_out)
+    };
+
+    let midpoint = node_count(sled::SplitPolicy::Midpoint)?;
+    let append = node_count(sled::SplitPolicy::AppendOptimized)?;
+    assert!(
+        append * 3 < midpoint * 2,
+        "expected append-optimized splits to use far fewer nodes \
+         than midpoint splits, but they used {} vs {}",
+        append,
+        midpoint
+    );
+
+    node_count(sled::SplitPolicy::Ratio(0.9))?;
+    assert!(
+        Config::new()
+            .temporary(true)
+            .split_policy(sled::SplitPolicy::Ratio(1.0))
+            .open()
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn iter_nodes_covers_keyspace() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..N {
+        db.insert(kv(i), kv(i))?;
+    }
+
+    let nodes = db.iter_nodes().collect::<Result<Vec<_>>>()?;
+    assert!(nodes.len() > 1);
+    assert!(nodes[0].lo.is_empty());
+    assert_eq!(nodes.last().unwrap().hi, None);
+    for pair in nodes.windows(2) {
+        assert_eq!(pair[0].hi.as_ref(), Some(&pair[1].lo));
+    }
+    assert_eq!(nodes.iter().map(|node| node.len).sum::<usize>(), N);
+    assert!(nodes.iter().all(|node| node.size > 0));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn remove_range_spanning_many_nodes() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 16][..])?;
+    }
+
+    let start = 1_000_u32.to_be_bytes();
+    let end = 9_000_u32.to_be_bytes();
+    assert_eq!(db.remove_range(start..end)?, 8_000);
+    assert_eq!(db.remove_range(start..end)?, 0);
+    assert_eq!(db.remove_range(end..start)?, 0);
+    assert_eq!(db.len(), 2_000);
+    assert_eq!(db.last_in_range(..end)?.unwrap().0, 999_u32.to_be_bytes());
+    assert_eq!(db.first_in_range(start..)?.unwrap().0, end);
+
+    assert_eq!(db.remove_range(..=start)?, 1_000);
+    assert_eq!(db.remove_range::<&[u8], _>(..)?, 1_000);
+    assert!(db.is_empty());
+
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 16][..])?;
+    }
+    assert_eq!(db.len(), 10_000);
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn transform_range_spanning_many_nodes() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &i.to_be_bytes())?;
+    }
+
+    let start = 1_000_u32.to_be_bytes();
+    let end = 9_000_u32.to_be_bytes();
+
+    // grow the even values and remove the odd ones
+    let changed = db.transform_range(start..end, |_k, v| {
+        if v[3] % 2 == 0 { Some([v, v].concat()) } else { None }
+    })?;
+    assert_eq!(changed, 8_000);
+    assert_eq!(db.len(), 6_000);
+
+    // returning the current value is not a change
+    assert_eq!(db.transform_range(start..end, |_k, v| Some(v.to_vec()))?, 0);
+    assert_eq!(db.transform_range(end..start, |_k, _v| None::<IVec>)?, 0);
+
+    for i in 0..10_000_u32 {
+        let expected = if (1_000..9_000).contains(&i) {
+            if i % 2 == 0 {
+                Some([i.to_be_bytes(), i.to_be_bytes()].concat())
+            } else {
+                None
+            }
+        } else {
+            Some(i.to_be_bytes().to_vec())
+        };
+        assert_eq!(db.get(i.to_be_bytes())?, expected.map(IVec::from));
+    }
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn temporary_in_removes_directory_on_drop() -> Result<()> {
+    common::setup_logger();
+
+    let mut base = std::env::temp_dir();
+    base.push("test_temporary_in_base");
+    let _ = std::fs::remove_dir_all(&base);
+
+    let db =
+        Config::new().temporary_in(&base).flush_every_ms(Some(1)).open()?;
+    let tree = db.open_tree(b"tree")?;
+    tree.insert(b"k", b"v")?;
+    db.flush()?;
+
+    let entries = || std::fs::read_dir(&base).unwrap().count();
+    assert_eq!(entries(), 1);
+
+    drop(db);
+    assert_eq!(entries(), 1, "a live Tree keeps the directory around");
+    drop(tree);
+    assert_eq!(entries(), 0);
+
+    std::fs::remove_dir_all(&base)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn async_db_round_trip() -> Result<()> {
+    use std::{
+        future::Future,
+        task::{Context, Poll, Wake, Waker},
+        thread::Thread,
+    };
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let waker =
+            Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+        let mut future = Box::pin(future);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    common::setup_logger();
+
+    let db = sled::AsyncDb::new(Config::new().temporary(true).open()?);
+
+    for i in 0..10_u8 {
+        assert_eq!(block_on(db.insert([i], vec![i]))?, None);
+    }
+    assert_eq!(block_on(db.get([3]))?, Some(IVec::from(&[3])));
+    assert!(block_on(db.contains_key([9]))?);
+    assert_eq!(block_on(db.remove([9]))?, Some(IVec::from(&[9])));
+
+    let start: &[u8] = &[2];
+    let range = block_on(db.range(start..))?;
+    assert_eq!(range.len(), 7);
+    assert_eq!(range[0], (IVec::from(&[2]), IVec::from(&[2])));
+
+    // dropping the future does not cancel the write
+    drop(db.insert([42], vec![42]));
+    block_on(db.flush())?;
+    let mut found = false;
+    for _ in 0..1000 {
+        if db.db().contains_key([42])? {
+            found = true;
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    assert!(found);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(target_os = "linux")]
+#[cfg_attr(miri, ignore)]
+fn flusher_thread_name_is_configurable() -> Result<()> {
+    common::setup_logger();
+
+    let thread_names = || -> Vec<String> {
+        std::fs::read_dir("/proc/self/task")
+            .unwrap()
+            .filter_map(|task| {
+                let comm = task.ok()?.path().join("comm");
+                std::fs::read_to_string(comm).ok()
+            })
+            .map(|name| name.trim_end().to_owned())
+            .collect()
+    };
+
+    let db = Config::new()
+        .temporary(true)
+        .flusher_thread_name("sled-test-fl".to_owned())
+        .flusher_stack_size(Some(256 * 1024))
+        .open()?;
+    db.insert(b"k", b"v")?;
+    db.flush()?;
+
+    assert!(thread_names().iter().any(|name| name == "sled-test-fl"));
+
+    drop(db);
+    assert!(!thread_names().iter().any(|name| name == "sled-test-fl"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn watch_with_filter() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    let watched = [b"user/17".to_vec(), b"user/4".to_vec()];
+    let mut subscriber = db.watch(move |key| watched.iter().any(|w| w == key));
+    let dropped = db.watch(|_| true);
+    drop(dropped);
+
+    db.insert(b"user/1", b"a")?;
+    db.insert(b"user/4", b"b")?;
+
+    let mut batch = sled::Batch::default();
+    batch.insert(b"user/2", b"c");
+    batch.remove(b"user/17");
+    db.apply_batch(batch)?;
+
+    db.insert(b"user/5", b"d")?;
+
+    let timeout = std::time::Duration::from_secs(5);
+
+    let event = subscriber.next_timeout(timeout).unwrap();
+    let keys: Vec<_> = event.iter().map(|(_, k, _)| k.clone()).collect();
+    assert_eq!(keys, vec![IVec::from(b"user/4")]);
+
+    let event = subscriber.next_timeout(timeout).unwrap();
+    let mut keys: Vec<_> = event.iter().map(|(_, k, _)| k.clone()).collect();
+    keys.sort();
+    assert_eq!(keys, vec![IVec::from(b"user/17"), IVec::from(b"user/2")]);
+
+    // keys written inside a batch may also be delivered
+    // individually, but unwatched keys never are
+    while let Ok(event) =
+        subscriber.next_timeout(std::time::Duration::from_millis(50))
+    {
+        for (_, key, _) in &event {
+            assert!(key == b"user/17" || key == b"user/2", "{:?}", key);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn coldest_leaves_are_resident_leaves_of_the_tree() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    let other = db.open_tree("other")?;
+
+    for i in 0..N {
+        db.insert(kv(i), kv(i))?;
+        other.insert(kv(i), kv(i))?;
+    }
+    for _ in 0..10 {
+        for i in 0..N {
+            assert!(db.get(kv(i))?.is_some());
+        }
+    }
+
+    let leaves = db.iter_nodes().collect::<Result<Vec<_>>>()?;
+    let other_pids = other
+        .iter_nodes()
+        .map(|node| node.map(|node| node.pid))
+        .collect::<Result<Vec<_>>>()?;
+
+    let coldest = db.coldest_leaves(usize::MAX)?;
+    assert!(!coldest.is_empty());
+    assert!(coldest.len() <= leaves.len());
+    for leaf in &coldest {
+        assert!(leaves.contains(leaf), "{:?} is not a leaf of the tree", leaf);
+        assert!(!other_pids.contains(&leaf.pid));
+    }
+    let mut pids = coldest.iter().map(|leaf| leaf.pid).collect::<Vec<_>>();
+    pids.sort_unstable();
+    pids.dedup();
+    assert_eq!(pids.len(), coldest.len());
+
+    assert_eq!(db.coldest_leaves(1)?.len(), 1);
+    assert_eq!(db.coldest_leaves(0)?, vec![]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn contains_prefix_matches_scan_prefix() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    assert!(!db.contains_prefix(b"")?);
+
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 16][..])?;
+    }
+
+    // leave gaps so that some leaves hold no keys at all for
+    // a prefix whose remaining keys live in a later leaf
+    db.remove_range(1_000_u32.to_be_bytes()..1_250_u32.to_be_bytes())?;
+    db.remove_range(2_048_u32.to_be_bytes()..2_303_u32.to_be_bytes())?;
+    db.remove_range(4_096_u32.to_be_bytes()..5_120_u32.to_be_bytes())?;
+
+    for prefix_len in 0..=3 {
+        for i in (0..12_000_u32).step_by(37) {
+            let prefix = &i.to_be_bytes()[..prefix_len];
+            assert_eq!(
+                db.contains_prefix(prefix)?,
+                db.scan_prefix(prefix).next().is_some(),
+                "disagreement for prefix {:?}",
+                prefix
+            );
+        }
+    }
+
+    let prefix = [0, 0, 2];
+    db.remove_range(&[0, 0, 2, 0][..]..&[0, 0, 2, 255][..])?;
+    assert!(db.contains_prefix(prefix)?);
+    db.remove(&[0, 0, 2, 255])?;
+    assert!(!db.contains_prefix(prefix)?);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn warm_on_open_pages_in_leaves() -> Result<()> {
+    common::setup_logger();
+
+    // each reopen needs its own policy, so reuse the path of one
+    // temporary config, which removes it once the test is done
+    let config = Config::new().temporary(true);
+    let path = config.get_path();
+
+    let open = |policy: sled::WarmPolicy| {
+        Config::new().path(&path).warm_on_open(policy).open()
+    };
+
+    let db = open(sled::WarmPolicy::None)?;
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 64][..])?;
+    }
+    db.flush()?;
+    let leaves = db.iter_nodes().count() as u64;
+    drop(db);
+
+    let cold = open(sled::WarmPolicy::None)?.node_stats();
+    assert!(cold.resident < leaves);
+
+    let db = open(sled::WarmPolicy::Full)?;
+    assert!(db.node_stats().resident >= leaves);
+    drop(db);
+
+    let db = open(sled::WarmPolicy::Budget(4096))?;
+    let budgeted = db.node_stats();
+    assert!(budgeted.resident > cold.resident);
+    assert!(budgeted.resident < leaves);
+    drop(db);
+
+    let db = open(sled::WarmPolicy::Prefix(vec![0, 0, 4]))?;
+    let prefixed = db.node_stats();
+    assert!(prefixed.resident > cold.resident);
+    assert!(prefixed.resident < leaves);
+
+    assert!(db.warm::<&[u8], _>(..)? > 0);
+    assert!(db.node_stats().resident >= leaves);
+    drop(db);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn len_fast_matches_len() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    assert_eq!(db.len_fast()?, 0);
+
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 16][..])?;
+    }
+    assert_eq!(db.len_fast()?, 10_000);
+
+    for i in (0..10_000_u32).step_by(3) {
+        db.remove(&i.to_be_bytes())?;
+    }
+    let mut batch = Batch::default();
+    for i in 10_000..10_500_u32 {
+        batch.insert(&i.to_be_bytes(), &[1][..]);
+    }
+    batch.remove(&1_u32.to_be_bytes());
+    db.apply_batch(batch)?;
+
+    assert_eq!(db.len_fast()?, db.len());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn batch_from_iterator_and_extend() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    db.insert(b"existing", b"old")?;
+
+    let mut batch: Batch = (0..2_500_u32)
+        .rev()
+        .map(|i| (i.to_be_bytes().to_vec(), i.to_le_bytes().to_vec()))
+        .collect();
+    batch.extend(vec![(b"existing".to_vec(), b"new".to_vec())]);
+    db.apply_batch(batch)?;
+
+    assert_eq!(db.len(), 2_501);
+    assert_eq!(db.get(b"existing")?.unwrap(), b"new");
+    assert_eq!(db.get(7_u32.to_be_bytes())?.unwrap(), 7_u32.to_le_bytes());
+
+    // a key repeated in the pairs keeps its last value
+    let batch: Batch = vec![(b"a", b"1"), (b"a", b"2")].into_iter().collect();
+    assert_eq!(batch.get(b"a"), Some(Some(&IVec::from(b"2"))));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn amplification_metrics() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    assert_eq!(db.write_amplification(), 0.0);
+
+    for i in 0..1_000_u32 {
+        db.insert(i.to_be_bytes(), vec![0; 100])?;
+    }
+    db.flush()?;
+
+    // every key and value was written to the log at least once
+    let write_amplification = db.write_amplification();
+    assert!(write_amplification >= 1.0, "{}", write_amplification);
+    assert!(db.space_amplification()? > 0.0);
+
+    Ok(())
+}
+
+#[test]
+fn db_into_iter_matches_iter() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    for i in 0..10_u8 {
+        db.insert([i], vec![i; 3])?;
+    }
+
+    let mut seen = vec![];
+    for kv_res in &db {
+        seen.push(kv_res?);
+    }
+
+    assert_eq!(seen, db.iter().collect::<Result<Vec<_>>>()?);
+    assert_eq!(seen, (&db).into_iter().collect::<Result<Vec<_>>>()?);
+    assert_eq!(seen.len(), 10);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn preallocated_heap_survives_restart() -> Result<()> {
+    common::setup_logger();
+
+    let mut path = std::env::temp_dir();
+    path.push("test_preallocated_heap");
+    let _ = std::fs::remove_dir_all(&path);
+
+    let value = vec![9_u8; 64 * 1024];
+    {
+        let db = Config::new().path(&path).open()?;
+        assert_eq!(db.preallocate_heap(value.len() as u64, 0)?, 0);
+
+        let preallocated = db.preallocate_heap(value.len() as u64, 1 << 20)?;
+        assert!(preallocated >= 1 << 20);
+
+        for i in 0..32_u8 {
+            db.insert([i], value.clone())?;
+        }
+        db.flush()?;
+    }
+
+    let db = Config::new().path(&path).open()?;
+    for i in 0..32_u8 {
+        assert_eq!(db.get([i])?, Some(IVec::from(value.clone())));
+    }
+    drop(db);
+
+    std::fs::remove_dir_all(&path).unwrap();
+
+    Ok(())
+}
+
+#[test]
+fn cas_reports_flat_outcome() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    assert_eq!(
+        db.cas(b"k", Some(b"v"), Some(b"w"))?,
+        CasOutcome::Mismatch { current: None }
+    );
+    assert_eq!(db.get(b"k")?, None);
+
+    let created = db.cas(b"k", None as Option<&[u8]>, Some(b"v"))?;
+    assert!(created.is_applied());
+    assert_eq!(created, CasOutcome::Applied { previous: None });
+
+    // a no-op swap still counts as applied
+    assert_eq!(
+        db.cas(b"k", Some(b"v"), Some(b"v"))?,
+        CasOutcome::Applied { previous: Some(IVec::from(b"v")) }
+    );
+
+    assert_eq!(
+        db.cas(b"k", Some(b"v"), None as Option<&[u8]>)?,
+        CasOutcome::Applied { previous: Some(IVec::from(b"v")) }
+    );
+    assert_eq!(db.get(b"k")?, None);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn flush_in_background_covers_earlier_writes() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).flush_every_ms(None).open()?;
+
+    // nothing left to flush
+    db.flush()?;
+    let flush = db.flush_in_background();
+    assert_eq!(flush.wait()?, 0);
+
+    db.insert(b"a", vec![1; 64])?;
+    let checkpoint = db.durable_checkpoint();
+    let flush = db.flush_in_background();
+    while !flush.is_complete() {
+        std::thread::yield_now();
+    }
+    assert!(flush.wait()? > 0);
+    assert!(db.durable_checkpoint() > checkpoint);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn apply_batch_durable_waits_for_batch() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    db.insert(b"before", b"0")?;
+
+    let mut batch = Batch::default();
+    batch.insert(b"marker", b"1");
+    batch.remove(b"before");
+
+    let checkpoint = db.apply_batch_durable(batch)?;
+    assert!(db.durable_at_least(&checkpoint));
+    assert!(db.durable_checkpoint() >= checkpoint);
+    assert_eq!(db.get(b"marker")?, Some(IVec::from(b"1")));
+    assert_eq!(db.get(b"before")?, None);
+
+    // an empty batch is already durable
+    let empty = db.apply_batch_durable(Batch::default())?;
+    assert!(empty >= checkpoint);
+    assert!(db.durable_at_least(&empty));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn for_each_in_range_matches_range() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    // sequential keys end up with computed, stride-encoded keys
+    // in their leaves, while the string keys share prefixes
+    for i in 0..5_000_u32 {
+        db.insert(&i.to_be_bytes(), &i.to_le_bytes())?;
+        db.insert(format!("key/{}", i), &[0; 4][..])?;
+    }
+    for i in (0..5_000_u32).step_by(7) {
+        db.remove(&i.to_be_bytes())?;
+        db.insert(format!("key/{}/new", i), &[1][..])?;
+    }
+
+    let collect = |lo: Vec<u8>, hi: Vec<u8>| -> Result<Vec<(IVec, IVec)>> {
+        let mut ret = vec![];
+        db.for_each_in_range(lo..hi, |k, v| {
+            ret.push((k.into(), v.into()));
+            std::ops::ControlFlow::Continue(())
+        })?;
+        Ok(ret)
+    };
+
+    let bounds: Vec<(Vec<u8>, Vec<u8>)> = vec![
+        (vec![], vec![255]),
+        (vec![0, 0, 1, 0], vec![0, 0, 9, 0]),
+        (b"key/1".to_vec(), b"key/2".to_vec()),
+        (b"key/4999".to_vec(), b"zzz".to_vec()),
+        (vec![9], vec![1]),
+    ];
+    for (lo, hi) in bounds {
+        let expected = db
+            .range(lo.clone()..hi.clone())
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(collect(lo, hi)?, expected);
+    }
+
+    let mut seen = 0;
+    db.for_each_in_range::<&[u8], _, _>(.., |_, _| {
+        seen += 1;
+        if seen == 10 {
+            std::ops::ControlFlow::Break(())
+        } else {
+            std::ops::ControlFlow::Continue(())
+        }
+    })?;
+    assert_eq!(seen, 10);
+
+    // the callback may write to the tree it is scanning
+    db.for_each_in_range(&b"key/10"[..]..&b"key/11"[..], |k, _| {
+        db.insert(&[255, 255], k).unwrap();
+        std::ops::ControlFlow::Continue(())
+    })?;
+    assert!(db.get([255, 255])?.is_some());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn page_out_drops_nodes_from_memory() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).flush_every_ms(None).open()?;
+    for i in 0..5_000_u32 {
+        db.insert(&i.to_be_bytes(), &[0; 64][..])?;
+    }
+    let leaves = db.iter_nodes().count();
+    assert!(leaves > 2);
+
+    let range = 1_000_u32.to_be_bytes()..2_000_u32.to_be_bytes();
+    let paged_out = db.page_out(range)?;
+    assert!(paged_out > 0);
+    assert!(paged_out < leaves);
+    assert!(db.node_stats().paged_out >= paged_out as u64);
+
+    assert!(db.page_out_all()? > 0);
+    assert_eq!(db.node_stats().resident, 0);
+
+    assert_eq!(db.len(), 5_000);
+    assert!(db.node_stats().resident as usize >= leaves);
+    assert_eq!(db.get(42_u32.to_be_bytes())?.unwrap(), &[0; 64][..]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn keys_and_values_match_full_items() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    for i in 0..2_000_u32 {
+        db.insert(&i.to_be_bytes(), vec![(i % 256) as u8; 100])?;
+    }
+    for i in (0..2_000_u32).step_by(5) {
+        db.insert(&i.to_be_bytes(), vec![0; 3])?;
+    }
+
+    let items = db.iter().collect::<Result<Vec<_>>>()?;
+    let keys = db.iter().keys().collect::<Result<Vec<_>>>()?;
+    let values = db.iter().values().collect::<Result<Vec<_>>>()?;
+    assert_eq!(keys, items.iter().map(|(k, _)| k.clone()).collect::<Vec<_>>());
+    assert_eq!(
+        values,
+        items.iter().map(|(_, v)| v.clone()).collect::<Vec<_>>()
+    );
+
+    let mut reversed = db.iter().keys().rev().collect::<Result<Vec<_>>>()?;
+    reversed.reverse();
+    assert_eq!(reversed, keys);
+
+    let start = 100_u32.to_be_bytes();
+    let end = 1_500_u32.to_be_bytes();
+    let mut both_ends = db.range(start..end).keys();
+    assert_eq!(both_ends.next().unwrap()?, start);
+    assert_eq!(both_ends.next_back().unwrap()?, 1_499_u32.to_be_bytes());
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn flush_timeout_reports_completion() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).flush_every_ms(None).open()?;
+    db.insert(b"k", vec![0; 1024])?;
+
+    let flushed = db.flush_timeout(Duration::from_secs(60))?;
+    assert!(flushed.unwrap() > 0);
+    assert_eq!(db.flush_timeout(Duration::from_secs(60))?, Some(0));
+
+    // a zero timeout may or may not see the flush finish, but
+    // must not fail or block
+    db.insert(b"k", vec![1; 1024])?;
+    let _ = db.flush_timeout(Duration::from_secs(0))?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn size_limits_reject_oversized_writes() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new()
+        .temporary(true)
+        .max_key_size(4)
+        .max_value_size(8)
+        .open()?;
+
+    assert!(matches!(
+        db.insert(b"12345", b"v"),
+        Err(Error::Io(std::io::ErrorKind::InvalidInput, _))
+    ));
+    assert!(matches!(
+        db.insert(b"k", vec![0; 9]),
+        Err(Error::Io(std::io::ErrorKind::InvalidInput, _))
+    ));
+    assert!(
+        db.compare_and_swap(b"k", None as Option<&[u8]>, Some(vec![0; 9]))
+            .is_err()
+    );
+    db.insert(b"k", vec![0; 8])?;
+    assert!(db.compare_and_append(b"k", 8, b"x").is_err());
+
+    fn concatenate(
+        _k: &[u8],
+        old: Option<&[u8]>,
+        new: &[u8],
+    ) -> Option<Vec<u8>> {
+        let mut ret = old.map_or(vec![], <[u8]>::to_vec);
+        ret.extend_from_slice(new);
+        Some(ret)
+    }
+    db.set_merge_operator(concatenate);
+    assert!(db.merge(b"k", b"x").is_err());
+
+    // an oversized write rejects the whole batch
+    let mut batch = Batch::default();
+    batch.insert(b"a", b"1");
+    batch.insert(b"b", vec![0; 9]);
+    assert!(db.apply_batch(batch).is_err());
+    assert_eq!(db.get(b"a")?, None);
+
+    let res: TransactionResult<()> = db.transaction(|tx| {
+        tx.insert(b"a", b"1")?;
+        tx.insert(b"12345", b"1")?;
+        Ok(())
+    });
+    assert!(res.is_err());
+    assert_eq!(db.get(b"a")?, None);
+
+    // removals are never limited
+    db.remove(b"k")?;
+    assert_eq!(db.len(), 0);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn conditional_batch_applies_all_or_nothing() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    db.insert(b"a", b"1")?;
+    db.insert(b"b", b"1")?;
+
+    let mut batch = ConditionalBatch::default();
+    batch.insert_if(b"a", Some(b"1"), b"2");
+    batch.remove_if(b"b", Some(b"2"));
+    batch.insert_if(b"c", Some(b"1"), b"1");
+    batch.insert_if(b"d", None as Option<&[u8]>, b"1");
+
+    let mismatches = db.apply_conditional_batch(batch)?.unwrap_err();
+    assert_eq!(
+        mismatches,
+        vec![
+            (IVec::from(b"b"), Some(IVec::from(b"1"))),
+            (IVec::from(b"c"), None)
+        ]
+    );
+    assert_eq!(db.get(b"a")?, Some(IVec::from(b"1")));
+    assert_eq!(db.get(b"d")?, None);
+
+    let mut batch = ConditionalBatch::default();
+    batch.insert_if(b"a", Some(b"1"), b"2");
+    batch.remove_if(b"b", Some(b"1"));
+    batch.insert_if(b"d", None as Option<&[u8]>, b"1");
+
+    db.apply_conditional_batch(batch)?.unwrap();
+    assert_eq!(db.get(b"a")?, Some(IVec::from(b"2")));
+    assert_eq!(db.get(b"b")?, None);
+    assert_eq!(db.get(b"d")?, Some(IVec::from(b"1")));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn recovery_progress_reports_replayed_log() -> Result<()> {
+    common::setup_logger();
+
+    let reports = Arc::new(Mutex::new(vec![]));
+    let reports2 = reports.clone();
+    let config = Config::new()
+        .temporary(true)
+        .snapshot_after_ops(1_000_000)
+        .recovery_progress(move |progress| {
+            reports2.lock().unwrap().push(progress);
+        });
+
+    let db = config.open()?;
+    assert_eq!(db.recovery_stats().log_entries, 0);
+    for i in 0..100_u32 {
+        db.insert(i.to_be_bytes(), vec![0; 64])?;
+    }
+    db.flush()?;
+    drop(db);
+
+    reports.lock().unwrap().clear();
+    let db = config.open()?;
+    let stats = db.recovery_stats();
+    assert!(stats.log_entries > 0);
+    assert!(stats.bytes_scanned > 100 * 64);
+    assert_eq!(reports.lock().unwrap().last(), Some(&stats));
+    assert_eq!(db.len(), 100);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn take_range_returns_removed_pairs() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in 0..10_000_u32 {
+        db.insert(&i.to_be_bytes(), &i.to_le_bytes())?;
+    }
+
+    let start = 1_000_u32.to_be_bytes();
+    let end = 9_000_u32.to_be_bytes();
+    let expected = db.range(start..end).collect::<Result<Vec<_>>>()?;
+
+    let taken = db.take_range(start..end)?;
+    assert_eq!(taken.len(), 8_000);
+    assert_eq!(taken, expected);
+    assert_eq!(db.take_range(start..end)?, vec![]);
+    assert_eq!(db.len(), 2_000);
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "metrics")]
+#[cfg_attr(miri, ignore)]
+fn metrics_snapshot_counts_operations() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+    let before = db.metrics_snapshot();
+
+    db.insert(b"a", b"1")?;
+    db.get(b"a")?;
+    db.remove(b"a")?;
+    db.flush()?;
+
+    let after = db.metrics_snapshot();
+    assert!(after.insert.count > before.insert.count);
+    assert!(after.get.count > before.get.count);
+    assert!(after.remove.count > before.remove.count);
+    assert!(after.flush.count > before.flush.count);
+    assert!(after.cache_hit_ratio() <= 1.0);
+
+    let encoded = after.encode_prometheus();
+    assert!(encoded.contains("# TYPE sled_op_latency_microseconds summary\n"));
+    assert!(
+        encoded.contains("sled_op_latency_microseconds_count{op=\"flush\"}")
+    );
+    assert!(encoded.contains("# TYPE sled_cache_hits_total counter\n"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn iter_from_matches_range() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for i in (0..2_000_u32).step_by(2) {
+        db.insert(&i.to_be_bytes(), &[0; 16][..])?;
+    }
+
+    for start in [0_u32, 1, 2, 999, 1000, 1998, 1999] {
+        let key = start.to_be_bytes();
+
+        let inclusive = db.iter_from(key, true).collect::<Result<Vec<_>>>()?;
+        let expected = db.range(key..).collect::<Result<Vec<_>>>()?;
+        assert_eq!(inclusive, expected);
+
+        let exclusive =
+            db.iter_from(key, false).rev().collect::<Result<Vec<_>>>()?;
+        let expected = db
+            .range::<&[u8], _>((
+                std::ops::Bound::Excluded(&key[..]),
+                std::ops::Bound::Unbounded,
+            ))
+            .rev()
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(exclusive, expected);
+        assert!(exclusive.iter().all(|(k, _)| &k[..] > &key[..]));
+    }
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn empty_key_is_an_ordinary_key() -> Result<()> {
+    common::setup_logger();
+
+    let mut path = std::env::temp_dir();
+    path.push("test_empty_key_is_an_ordinary_key");
+    let _ = std::fs::remove_dir_all(&path);
+
+    let db = Config::new().path(&path).open()?;
+    assert_eq!(db.get(b"")?, None);
+    assert_eq!(db.remove(b"")?, None);
+
+    assert_eq!(db.insert(b"", b"empty")?, None);
+    assert_eq!(db.get(b"")?, Some(IVec::from(b"empty")));
+    assert_eq!(db.len(), 1);
+
+    // enough keys to split the leftmost leaf several times
+    for i in 0..10_000_u32 {
+        db.insert(i.to_be_bytes(), &[1][..])?;
+    }
+    assert_eq!(db.get(b"")?, Some(IVec::from(b"empty")));
+    assert_eq!(db.first()?.unwrap().0, IVec::from(b""));
+    assert_eq!(db.get_lt(0_u32.to_be_bytes())?.unwrap().0, IVec::from(b""));
+    assert_eq!(db.get_gt(b"")?.unwrap().0, 0_u32.to_be_bytes());
+    assert_eq!(db.iter().next_back().unwrap()?.0, 9_999_u32.to_be_bytes());
+    assert_eq!(db.iter().rev().last().unwrap()?.0, IVec::from(b""));
+
+    let empty: &[u8] = b"";
+    assert_eq!(db.range(empty..=empty).count(), 1);
+    assert_eq!(db.range(..=empty).count(), 1);
+    assert_eq!(db.range(..empty).count(), 0);
+    assert_eq!(db.range(empty..).count(), 10_001);
+    assert_eq!(db.scan_prefix(b"").count(), 10_001);
+
+    assert_eq!(
+        db.compare_and_swap(b"", Some(b"empty"), Some(b"swapped"))?,
+        Ok(())
+    );
+    drop(db);
+
+    let db = Config::new().path(&path).open()?;
+    assert_eq!(db.get(b"")?, Some(IVec::from(b"swapped")));
+    assert_eq!(db.remove(b"")?, Some(IVec::from(b"swapped")));
+    assert_eq!(db.get(b"")?, None);
+    assert_eq!(db.first()?.unwrap().0, 0_u32.to_be_bytes());
+    assert_eq!(db.len(), 10_000);
+    assert_eq!(db.verify_integrity()?, vec![]);
+    drop(db);
+
+    std::fs::remove_dir_all(&path)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn rename_prefix_with_overlapping_prefixes() -> Result<()> {
+    common::setup_logger();
+
+    let config = Config::new().temporary(true);
+    let db = config.open()?;
+
+    for i in 0..1_000_u32 {
+        let mut key = b"a".to_vec();
+        key.extend_from_slice(&i.to_be_bytes());
+        db.insert(key, &i.to_be_bytes())?;
+    }
+    db.insert(b"ab", b"collides")?;
+    db.insert(b"b", b"untouched")?;
+
+    // moved keys still start with the source prefix, so each
+    // must be moved exactly once
+    assert_eq!(db.rename_prefix(b"a", b"ab")?, 1_001);
+    assert_eq!(db.len(), 1_002);
+    assert_eq!(db.get(b"ab")?, None);
+    assert_eq!(db.get(b"abb")?, Some(IVec::from(b"collides")));
+    assert_eq!(db.get(b"b")?, Some(IVec::from(b"untouched")));
+    for i in 0..1_000_u32 {
+        let mut key = b"ab".to_vec();
+        key.extend_from_slice(&i.to_be_bytes());
+        assert_eq!(db.get(key)?, Some(IVec::from(&i.to_be_bytes())));
+    }
+
+    assert_eq!(db.rename_prefix(b"missing", b"x")?, 0);
+    assert_eq!(db.len(), 1_002);
+    assert_eq!(db.verify_integrity()?, vec![]);
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn stored_size_of_range_counts_covering_leaves() -> Result<()> {
+    common::setup_logger();
+
+    let mut path = std::env::temp_dir();
+    path.push("test_stored_size_of_range");
+    let _ = std::fs::remove_dir_all(&path);
+
+    let db = Config::new().path(&path).open()?;
+    let all = db.stored_size_of_range::<&[u8], _>(..)?;
+    assert!(all > 0);
+
+    for i in 0..10_000_u32 {
+        db.insert(i.to_be_bytes(), &[0; 64][..])?;
+    }
+
+    let all = db.stored_size_of_range::<&[u8], _>(..)?;
+    let first_half =
+        db.stored_size_of_range(..5_000_u32.to_be_bytes().to_vec())?;
+    let second_half =
+        db.stored_size_of_range(5_000_u32.to_be_bytes().to_vec()..)?;
+    let single =
+        db.stored_size_of_range(7_u32.to_be_bytes()..=7_u32.to_be_bytes())?;
+
+    // the values alone take up more than this on disk
+    assert!(all >= 640_000, "{}", all);
+    assert!(single > 0 && single < first_half);
+    assert!(first_half < all && second_half < all);
+    // the leaf holding the split point is counted in both
+    assert!(first_half + second_half >= all);
+    drop(db);
+
+    // sizes come from disk, so leaves that are not resident in
+    // a cold database are still counted. the total may differ
+    // a little, since walking the leaves can split some of them.
+    let db = Config::new().path(&path).open()?;
+    let cold = db.stored_size_of_range::<&[u8], _>(..)?;
+    assert!(cold >= 640_000, "{}", cold);
+    drop(db);
+
+    std::fs::remove_dir_all(&path)?;
+
+    Ok(())
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn prefix_stats_groups_keys_by_prefix() -> Result<()> {
+    common::setup_logger();
+
+    let db = Config::new().temporary(true).open()?;
+
+    for tenant in 0..10_u8 {
+        for i in 0..u32::from(tenant) * 100 {
+            let mut key = vec![tenant];
+            key.extend_from_slice(&i.to_be_bytes());
+            db.insert(key, vec![0; 11])?;
+        }
+    }
+    db.insert(b"", b"x")?;
+
+    let stats = db.prefix_stats(1)?;
+    assert_eq!(stats.len(), 10);
+    assert_eq!(stats[&IVec::from(b"")], (1, 1));
+    for tenant in 1..10_u8 {
+        let count = u64::from(tenant) * 100;
+        assert_eq!(stats[&IVec::from(&[tenant])], (count, count * 16));
+    }
+
+    let total: u64 = stats.values().map(|(count, _)| count).sum();
+    assert_eq!(total as usize, db.len());
+
+    Ok(())
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn tree_import_export() -> Result<()> {